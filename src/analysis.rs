@@ -0,0 +1,254 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    io::{self, Write},
+    mem::discriminant,
+    path::PathBuf,
+};
+
+use crate::{layout::Layout, replay::Replay};
+
+/// Statistics of a single header byte nobody decodes yet.
+#[derive(Debug, Clone)]
+pub struct OffsetStats {
+    pub offset: usize,
+    pub distinct: usize,
+    /// Shannon entropy in bits, 0 for a constant byte and 8 at most.
+    pub entropy: f64,
+    pub most_common: u8,
+    pub most_common_share: f64,
+    /// Pearson correlation with the level, `None` when either side is constant.
+    pub level_correlation: Option<f64>,
+    /// Normalized mutual information (0 = independent, 1 = fully determined) with known fields.
+    pub mode_dependence: f64,
+    pub rule_dependence: f64,
+    pub modifier_dependence: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct UnknownByteReport {
+    /// Replays the statistics are computed over.
+    pub samples: usize,
+    /// Files that were unreadable, from another build or failed to parse.
+    pub skipped: usize,
+    pub offsets: Vec<OffsetStats>,
+}
+
+impl UnknownByteReport {
+    /// Only the header is analysed, up to the furthest field the layout knows.
+    pub fn from_paths(paths: &[PathBuf], layout: &Layout) -> Self {
+        let mut samples = Vec::with_capacity(paths.len());
+        let mut skipped = 0;
+        for path in paths {
+            let Ok(bytes) = std::fs::read(path) else {
+                skipped += 1;
+                continue;
+            };
+            if !layout.matches(&bytes) {
+                skipped += 1;
+                continue;
+            }
+            match Replay::from_bytes_with(&bytes, layout) {
+                Ok(replay) => samples.push((bytes, replay)),
+                Err(_) => skipped += 1,
+            }
+        }
+        let mut report = Self::from_samples(&samples, layout);
+        report.skipped += skipped;
+        report
+    }
+
+    pub fn from_samples(samples: &[(Vec<u8>, Replay)], layout: &Layout) -> Self {
+        let header_len = layout.header_len();
+        let samples: Vec<&(Vec<u8>, Replay)> = samples
+            .iter()
+            .filter(|(bytes, _)| bytes.len() >= header_len)
+            .collect();
+        let levels: Vec<f64> = samples.iter().map(|(_, r)| r.level as f64).collect();
+        let modes: Vec<_> = samples.iter().map(|(_, r)| discriminant(&r.mode)).collect();
+        let rules: Vec<_> = samples.iter().map(|(_, r)| r.rule).collect();
+        let modifiers: Vec<_> = samples.iter().map(|(_, r)| r.modifiers.clone()).collect();
+
+        let offsets = (0..header_len)
+            .filter(|offset| !layout.claims(*offset))
+            .map(|offset| {
+                let values: Vec<u8> = samples.iter().map(|(bytes, _)| bytes[offset]).collect();
+                let mut histogram = [0usize; 256];
+                values.iter().for_each(|v| histogram[*v as usize] += 1);
+                let (most_common, count) = histogram
+                    .iter()
+                    .enumerate()
+                    .max_by_key(|(value, count)| (**count, std::cmp::Reverse(*value)))
+                    .map(|(value, count)| (value as u8, *count))
+                    .unwrap_or_default();
+                let as_f64: Vec<f64> = values.iter().map(|v| *v as f64).collect();
+                OffsetStats {
+                    offset,
+                    distinct: histogram.iter().filter(|c| **c > 0).count(),
+                    entropy: entropy(histogram.iter().copied(), values.len()),
+                    most_common,
+                    most_common_share: if values.is_empty() {
+                        0.0
+                    } else {
+                        count as f64 / values.len() as f64
+                    },
+                    level_correlation: pearson(&as_f64, &levels),
+                    mode_dependence: dependence(&values, &modes),
+                    rule_dependence: dependence(&values, &rules),
+                    modifier_dependence: dependence(&values, &modifiers),
+                }
+            })
+            .collect();
+
+        Self {
+            samples: samples.len(),
+            skipped: 0,
+            offsets,
+        }
+    }
+
+    pub fn write_csv(&self, mut w: impl Write) -> io::Result<()> {
+        writeln!(
+            w,
+            "offset,distinct,entropy,most_common,most_common_share,level_correlation,mode_dependence,rule_dependence,modifier_dependence"
+        )?;
+        for s in &self.offsets {
+            writeln!(
+                w,
+                "0x{:03X},{},{:.4},0x{:02X},{:.4},{},{:.4},{:.4},{:.4}",
+                s.offset,
+                s.distinct,
+                s.entropy,
+                s.most_common,
+                s.most_common_share,
+                s.level_correlation
+                    .map(|c| format!("{c:.4}"))
+                    .unwrap_or_default(),
+                s.mode_dependence,
+                s.rule_dependence,
+                s.modifier_dependence,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn entropy(counts: impl Iterator<Item = usize>, total: usize) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    counts
+        .filter(|c| *c > 0)
+        .map(|c| {
+            let p = c as f64 / total as f64;
+            p * (1.0 / p).log2()
+        })
+        .sum()
+}
+
+fn pearson(xs: &[f64], ys: &[f64]) -> Option<f64> {
+    let n = xs.len() as f64;
+    if xs.is_empty() {
+        return None;
+    }
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = ys.iter().sum::<f64>() / n;
+    let (mut cov, mut var_x, mut var_y) = (0.0, 0.0, 0.0);
+    for (x, y) in xs.iter().zip(ys) {
+        cov += (x - mean_x) * (y - mean_y);
+        var_x += (x - mean_x).powi(2);
+        var_y += (y - mean_y).powi(2);
+    }
+    if var_x == 0.0 || var_y == 0.0 {
+        return None;
+    }
+    Some(cov / (var_x * var_y).sqrt())
+}
+
+/// Mutual information between a byte and a categorical field, divided by the smaller entropy.
+fn dependence<K: Hash + Eq>(values: &[u8], keys: &[K]) -> f64 {
+    let mut by_value: HashMap<u8, usize> = HashMap::new();
+    let mut by_key: HashMap<&K, usize> = HashMap::new();
+    let mut joint: HashMap<(u8, &K), usize> = HashMap::new();
+    for (value, key) in values.iter().zip(keys) {
+        *by_value.entry(*value).or_default() += 1;
+        *by_key.entry(key).or_default() += 1;
+        *joint.entry((*value, key)).or_default() += 1;
+    }
+    let total = values.len();
+    let h_value = entropy(by_value.into_values(), total);
+    let h_key = entropy(by_key.into_values(), total);
+    let h_joint = entropy(joint.into_values(), total);
+    let smallest = h_value.min(h_key);
+    if smallest == 0.0 {
+        return 0.0;
+    }
+    ((h_value + h_key - h_joint) / smallest).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout;
+
+    fn sample(level: u32, rule: u8, unknown: u8) -> (Vec<u8>, Replay) {
+        let mut bytes = vec![0u8; layout::V1.header_len()];
+        bytes[0x18] = 1; // a non-zero timestamp
+        bytes[0x24] = 0x01; // master
+        bytes[0x28] = rule;
+        bytes[0x3C..0x40].copy_from_slice(&level.to_le_bytes());
+        bytes[0x50] = unknown;
+        let replay = Replay::from_bytes(&bytes).unwrap();
+        (bytes, replay)
+    }
+
+    #[test]
+    fn test_entropy() {
+        assert_eq!(entropy([4].into_iter(), 4), 0.0);
+        assert_eq!(entropy([2, 2].into_iter(), 4), 1.0);
+        assert_eq!(entropy([1, 1, 1, 1].into_iter(), 4), 2.0);
+    }
+
+    #[test]
+    fn test_claimed_offsets_are_skipped() {
+        let report = UnknownByteReport::from_samples(&[sample(10, 0, 1)], &layout::V1);
+        assert!(report.offsets.iter().all(|s| !layout::V1.claims(s.offset)));
+        assert!(report.offsets.iter().any(|s| s.offset == 0x50));
+        assert!(!report.offsets.iter().any(|s| s.offset == 0x24));
+    }
+
+    #[test]
+    fn test_correlations() {
+        let samples = [
+            sample(100, 0, 1),
+            sample(200, 1, 2),
+            sample(300, 0, 3),
+            sample(400, 1, 4),
+        ];
+        let report = UnknownByteReport::from_samples(&samples, &layout::V1);
+        let stats = report.offsets.iter().find(|s| s.offset == 0x50).unwrap();
+        assert_eq!(report.samples, 4);
+        assert_eq!(stats.distinct, 4);
+        assert_eq!(stats.entropy, 2.0);
+        assert!((stats.level_correlation.unwrap() - 1.0).abs() < 1e-9);
+        assert_eq!(stats.rule_dependence, 1.0);
+        assert_eq!(stats.mode_dependence, 0.0);
+
+        let constant = report.offsets.iter().find(|s| s.offset == 0x51).unwrap();
+        assert_eq!(constant.entropy, 0.0);
+        assert_eq!(constant.most_common, 0);
+        assert_eq!(constant.most_common_share, 1.0);
+        assert_eq!(constant.level_correlation, None);
+    }
+
+    #[test]
+    fn test_csv() {
+        let report = UnknownByteReport::from_samples(&[sample(10, 0, 7)], &layout::V1);
+        let mut out = Vec::new();
+        report.write_csv(&mut out).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        assert!(csv.starts_with("offset,distinct,entropy"));
+        assert!(csv.contains("\n0x050,1,0.0000,0x07,1.0000,,"));
+        assert_eq!(csv.lines().count(), report.offsets.len() + 1);
+    }
+}
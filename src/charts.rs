@@ -0,0 +1,319 @@
+//! Progress over time for one mode and rule, what we used to build by hand in spreadsheets.
+
+use chrono::DateTime;
+use egui_plot::{GridMark, Legend, Line, Plot, PlotPoints, Points};
+
+use crate::{
+    layout,
+    replay::{GameTime, KonohaDifficulty, Mod, Mode, Replay, Rule},
+    stats,
+};
+
+const MODES: [Mode; 8] = [
+    Mode::Normal,
+    Mode::Marathon,
+    Mode::Master,
+    Mode::Konoha(KonohaDifficulty::Easy),
+    Mode::Konoha(KonohaDifficulty::Hard),
+    Mode::Shiranui(0, 0),
+    Mode::Asuka,
+    Mode::Versus,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    Level,
+    Score,
+    /// Plotted in seconds.
+    Time,
+}
+
+impl Metric {
+    pub fn value(self, replay: &Replay) -> f64 {
+        match self {
+            Metric::Level => replay.level as f64,
+            Metric::Score => replay.score as f64,
+            Metric::Time => replay.time.0 as f64 / GameTime::FPS as f64,
+        }
+    }
+
+    /// Whether `replay` is a new PB over `best`. Time follows the best-ranked run by
+    /// [`stats::compare`] like the Bests tab, so it goes up when a longer run gets further.
+    pub fn improves(self, replay: &Replay, best: &Replay) -> bool {
+        match self {
+            Metric::Time => stats::compare(replay, best).is_gt(),
+            _ => self.value(replay) > self.value(best),
+        }
+    }
+
+    /// Name of the [`pb_steps`] line.
+    pub fn pb_label(self) -> &'static str {
+        match self {
+            Metric::Time => "Time of the best-ranked run",
+            _ => "PB",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Presence {
+    Any,
+    With,
+    Without,
+}
+
+/// What the chart shows, kept between frames.
+#[derive(Debug, Clone)]
+pub struct ChartFilter {
+    pub mode: Mode,
+    pub rule: Rule,
+    pub metric: Metric,
+    /// Replays in the rolling average.
+    pub window: usize,
+    pub modifiers: Vec<(Mod, Presence)>,
+}
+
+impl Default for ChartFilter {
+    fn default() -> Self {
+        Self {
+            mode: Mode::Master,
+            rule: Rule::Tgm,
+            metric: Metric::Level,
+            window: 10,
+            modifiers: layout::CURRENT
+                .modifier_bits
+                .iter()
+                .map(|(_, m)| (*m, Presence::Any))
+                .collect(),
+        }
+    }
+}
+
+impl ChartFilter {
+    pub fn matches(&self, replay: &Replay) -> bool {
+        replay.mode.group() == self.mode.group()
+            && replay.rule == self.rule
+            && self.modifiers.iter().all(|(m, presence)| match presence {
+                Presence::Any => true,
+                Presence::With => replay.modifiers.contains(m),
+                Presence::Without => !replay.modifiers.contains(m),
+            })
+    }
+
+    /// The matching replays, oldest first.
+    pub fn replays<'a>(&self, replays: impl IntoIterator<Item = &'a Replay>) -> Vec<&'a Replay> {
+        let mut replays: Vec<&Replay> = replays.into_iter().filter(|r| self.matches(r)).collect();
+        replays.sort_by_key(|r| r.played_at);
+        replays
+    }
+
+    /// `[played_at as unix seconds, metric]`, oldest first.
+    pub fn points<'a>(&self, replays: impl IntoIterator<Item = &'a Replay>) -> Vec<[f64; 2]> {
+        self.replays(replays)
+            .into_iter()
+            .map(|r| point(r, self.metric))
+            .collect()
+    }
+}
+
+fn point(replay: &Replay, metric: Metric) -> [f64; 2] {
+    [replay.played_at.timestamp() as f64, metric.value(replay)]
+}
+
+/// Mean of each point and the `window - 1` before it.
+pub fn rolling_average(points: &[[f64; 2]], window: usize) -> Vec<[f64; 2]> {
+    let window = window.max(1);
+    points
+        .iter()
+        .enumerate()
+        .map(|(i, [x, _])| {
+            let slice = &points[(i + 1).saturating_sub(window)..=i];
+            [
+                *x,
+                slice.iter().map(|p| p[1]).sum::<f64>() / slice.len() as f64,
+            ]
+        })
+        .collect()
+}
+
+/// The best so far as a staircase over replays sorted oldest first, stepping at every new PB.
+pub fn pb_steps(replays: &[&Replay], metric: Metric) -> Vec<[f64; 2]> {
+    let mut steps: Vec<[f64; 2]> = Vec::new();
+    let mut best: Option<&Replay> = None;
+    for replay in replays {
+        if best.is_none_or(|b| metric.improves(replay, b)) {
+            let [x, y] = point(replay, metric);
+            if let Some(b) = best {
+                steps.push([x, metric.value(b)]);
+            }
+            steps.push([x, y]);
+            best = Some(replay);
+        }
+    }
+    if let (Some(last), Some(b)) = (replays.last(), best) {
+        let end = [point(last, metric)[0], metric.value(b)];
+        if steps.last() != Some(&end) {
+            steps.push(end);
+        }
+    }
+    steps
+}
+
+pub fn show<'a>(
+    ui: &mut egui::Ui,
+    filter: &mut ChartFilter,
+    replays: impl IntoIterator<Item = &'a Replay>,
+) {
+    ui.horizontal_wrapped(|ui| {
+        egui::ComboBox::from_id_salt("chart_mode")
+            .selected_text(filter.mode.to_string())
+            .show_ui(ui, |ui| {
+                for mode in MODES {
+                    ui.selectable_value(&mut filter.mode, mode, mode.to_string());
+                }
+            });
+        ui.selectable_value(&mut filter.rule, Rule::Standard, "Standard");
+        ui.selectable_value(&mut filter.rule, Rule::Tgm, "TGM");
+        ui.separator();
+        ui.selectable_value(&mut filter.metric, Metric::Level, "Level");
+        ui.selectable_value(&mut filter.metric, Metric::Score, "Score");
+        ui.selectable_value(&mut filter.metric, Metric::Time, "Time");
+        ui.separator();
+        ui.add(
+            egui::DragValue::new(&mut filter.window)
+                .range(1..=100)
+                .prefix("Average of "),
+        );
+        ui.separator();
+        for (modifier, presence) in &mut filter.modifiers {
+            egui::ComboBox::from_id_salt(("chart_modifier", modifier.to_string()))
+                .selected_text(format!("{modifier}: {presence:?}"))
+                .show_ui(ui, |ui| {
+                    for option in [Presence::Any, Presence::With, Presence::Without] {
+                        ui.selectable_value(presence, option, format!("{option:?}"));
+                    }
+                });
+        }
+    });
+
+    let replays = filter.replays(replays);
+    let points: Vec<[f64; 2]> = replays.iter().map(|r| point(r, filter.metric)).collect();
+    if points.is_empty() {
+        ui.label("No replays match.");
+        return;
+    }
+    let metric = filter.metric;
+    let format_y = move |y: f64| match metric {
+        Metric::Time => GameTime((y * GameTime::FPS as f64) as u32).to_string(),
+        _ => format!("{y:.0}"),
+    };
+    let format_x = |x: f64| {
+        DateTime::from_timestamp(x as i64, 0)
+            .map(|d| d.format("%Y-%m-%d").to_string())
+            .unwrap_or_default()
+    };
+    Plot::new("progress")
+        .legend(Legend::default())
+        .x_axis_formatter(move |mark: GridMark, _| format_x(mark.value))
+        .y_axis_formatter(move |mark: GridMark, _| format_y(mark.value))
+        .label_formatter(move |name, point| {
+            format!("{name}\n{}\n{}", format_x(point.x), format_y(point.y))
+        })
+        .show(ui, |plot| {
+            plot.points(
+                Points::new(PlotPoints::from(points.clone()))
+                    .radius(2.5)
+                    .name("Replays"),
+            );
+            plot.line(
+                Line::new(PlotPoints::from(rolling_average(&points, filter.window)))
+                    .name(format!("Average of {}", filter.window)),
+            );
+            plot.line(
+                Line::new(PlotPoints::from(pb_steps(&replays, metric))).name(metric.pb_label()),
+            );
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::tests::replay;
+
+    #[test]
+    fn test_series() {
+        let points = [[0.0, 100.0], [1.0, 300.0], [2.0, 200.0], [3.0, 400.0]];
+        assert_eq!(
+            rolling_average(&points, 2),
+            [[0.0, 100.0], [1.0, 200.0], [2.0, 250.0], [3.0, 300.0]]
+        );
+        assert_eq!(rolling_average(&points, 0), points);
+        assert!(pb_steps(&[], Metric::Level).is_empty());
+    }
+
+    #[test]
+    fn test_pb_steps() {
+        let x = |seconds: f64| 1_700_000_000.0 + seconds;
+        let mut faster = replay(Mode::Master, 1, 400, 0, 50);
+        faster.time = GameTime(30 * 60);
+        let replays = [
+            replay(Mode::Master, 1, 100, 0, 10),
+            replay(Mode::Master, 1, 300, 0, 20),
+            replay(Mode::Master, 1, 200, 0, 30),
+            replay(Mode::Master, 1, 400, 0, 40),
+            faster,
+        ];
+        let replays: Vec<&Replay> = replays.iter().collect();
+        assert_eq!(
+            pb_steps(&replays, Metric::Level),
+            [
+                [x(10.0), 100.0],
+                [x(20.0), 100.0],
+                [x(20.0), 300.0],
+                [x(40.0), 300.0],
+                [x(40.0), 400.0],
+                [x(50.0), 400.0]
+            ]
+        );
+        // follows the best-ranked run, not the 10 second top-out, and goes up with it
+        assert_eq!(
+            pb_steps(&replays, Metric::Time),
+            [
+                [x(10.0), 10.0],
+                [x(20.0), 10.0],
+                [x(20.0), 20.0],
+                [x(40.0), 20.0],
+                [x(40.0), 40.0],
+                [x(50.0), 40.0],
+                [x(50.0), 30.0]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_filter() {
+        let mut big = replay(Mode::Master, 1, 999, 0, 600);
+        big.modifiers = vec![Mod::Big];
+        let replays = [
+            replay(Mode::Master, 1, 500, 0, 300),
+            big,
+            replay(Mode::Shiranui(2, 10), 1, 200, 0, 100),
+        ];
+        let mut filter = ChartFilter::default();
+        assert_eq!(
+            filter.points(&replays),
+            [[1_700_000_300.0, 500.0], [1_700_000_600.0, 999.0]]
+        );
+
+        filter.modifiers.iter_mut().for_each(|(m, presence)| {
+            if *m == Mod::Big {
+                *presence = Presence::Without;
+            }
+        });
+        filter.metric = Metric::Time;
+        assert_eq!(filter.points(&replays), [[1_700_000_300.0, 300.0]]);
+
+        filter.mode = Mode::Shiranui(0, 0);
+        assert_eq!(filter.points(&replays).len(), 1);
+    }
+}
@@ -0,0 +1,156 @@
+use std::{fs::File, io::BufWriter};
+
+use crate::{
+    analysis::UnknownByteReport, config::Config, diagnostics::Diagnostics, layout,
+    names::LocalSteam, replay::ReplayStore, stats::sessions, steam_id::SteamId,
+};
+
+const USAGE: &str = "Usage:
+  tgm4-replay-manager                          Start the GUI
+  tgm4-replay-manager --unknown-bytes <csv>    Write statistics of undecoded header bytes
+  tgm4-replay-manager --dump <replay.bin>      Print every known header field of a replay
+  tgm4-replay-manager --sessions [steam id]    Summarise play sessions, yours by default";
+
+/// Runs a command line subcommand instead of the GUI.
+pub fn run(args: &[String]) {
+    attach_console();
+    match args {
+        [flag, path] if flag == "--unknown-bytes" => unknown_bytes(path),
+        [flag, path] if flag == "--dump" => dump(path),
+        [flag] if flag == "--sessions" => print_sessions(None),
+        [flag, id] if flag == "--sessions" => print_sessions(Some(id)),
+        _ => eprintln!("{USAGE}"),
+    }
+}
+
+/// Release builds are GUI programs on Windows, so without a console of their own
+/// nothing printed would show up in the terminal that started them.
+#[cfg(windows)]
+fn attach_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    // fails when started from Explorer or when a console is already there, both are fine
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
+
+fn unknown_bytes(path: &str) {
+    let mut diagnostics = Diagnostics::default();
+    let paths = ReplayStore::replay_paths(&mut diagnostics);
+    for d in diagnostics.iter() {
+        eprintln!("{}: {}", d.kind, d.message);
+    }
+    let report = UnknownByteReport::from_paths(&paths, layout::CURRENT);
+    let file = match File::create(path) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("Error creating {path}: {e}");
+            return;
+        }
+    };
+    if let Err(e) = report.write_csv(BufWriter::new(file)) {
+        eprintln!("Error writing {path}: {e}");
+        return;
+    }
+    println!(
+        "Analysed {} replays ({} skipped), wrote {} offsets to {path}",
+        report.samples,
+        report.skipped,
+        report.offsets.len()
+    );
+}
+
+fn dump(path: &str) {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("Error reading {path}: {e}");
+            return;
+        }
+    };
+    let layout = layout::CURRENT;
+    println!("{path} ({} bytes, layout {})", bytes.len(), layout.name);
+    for spec in layout.fields {
+        let hex = bytes
+            .get(spec.range())
+            .map(|raw| {
+                raw.iter()
+                    .map(|b| format!("{b:02X}"))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .unwrap_or_else(|| String::from("--"));
+        let value = layout
+            .read(&bytes, spec.field)
+            .map(|v| spec.describe(v))
+            .unwrap_or_else(|e| e.to_string());
+        println!(
+            "0x{:03X} {:<16} {:<24} {value}",
+            spec.offset,
+            spec.field.to_string(),
+            hex
+        );
+    }
+}
+
+fn print_sessions(id: Option<&str>) {
+    let config = Config::load();
+    let id = match id.map(str::parse::<SteamId>) {
+        Some(Ok(id)) => id,
+        Some(Err(e)) => {
+            eprintln!("{e}");
+            return;
+        }
+        None => match config.my_steam_id.or_else(|| {
+            config
+                .steam_path
+                .clone()
+                .or_else(LocalSteam::default_root)
+                .and_then(|root| LocalSteam::load(&root).most_recent_user())
+        }) {
+            Some(id) => id,
+            None => {
+                eprintln!("Set my_steam_id in the config or pass a Steam ID");
+                return;
+            }
+        },
+    };
+    let store = ReplayStore::new();
+    let sessions = sessions(id, store.all(), config.session_gap());
+    if sessions.is_empty() {
+        println!("No replays of {id}");
+        return;
+    }
+    for session in &sessions {
+        println!(
+            "{} to {}, {} games, {} played",
+            session.start().format("%Y-%m-%d %H:%M"),
+            session.end().format("%H:%M"),
+            session.replays.len(),
+            session.playtime()
+        );
+        for (key, best) in &session.bests {
+            let (mode, rule) = key;
+            let progress = session
+                .progress
+                .get(key)
+                .map(|p| p.to_string())
+                .unwrap_or_else(|| String::from("first time"));
+            println!(
+                "  {:<16} {:<8} level {:>4}  score {:>8}  {}  ({progress})",
+                mode,
+                rule.to_string(),
+                best.level,
+                best.score,
+                best.time
+            );
+        }
+    }
+}
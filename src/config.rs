@@ -0,0 +1,69 @@
+use std::{env, fs, path::PathBuf};
+
+use chrono::TimeDelta;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    steam::DEFAULT_BASE_URL,
+    steam_id::{SteamId, SteamIdFormat},
+};
+
+/// User settings, read from `config.json` in the config dir. Missing keys take their default.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Steam Web API key, the `APIKEY` environment variable takes precedence.
+    pub api_key: String,
+    /// Root of the Steam Web API, only worth changing to point at a proxy or test server.
+    pub steam_api_base_url: String,
+    /// How long a fetched persona name is trusted before asking the Web API again.
+    pub name_cache_ttl_hours: u64,
+    /// Steam client install to read local names from, found automatically when unset.
+    pub steam_path: Option<PathBuf>,
+    /// How Steam IDs are shown: `Id64`, `Id32` or `Id3`.
+    pub steam_id_format: SteamIdFormat,
+    /// SteamID64 the friends filter is relative to, defaults to the last account logged into Steam.
+    pub my_steam_id: Option<SteamId>,
+    /// A longer break between two replays starts a new play session.
+    pub session_gap_minutes: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            api_key: String::new(),
+            steam_api_base_url: String::from(DEFAULT_BASE_URL),
+            name_cache_ttl_hours: 24 * 7,
+            steam_path: None,
+            steam_id_format: SteamIdFormat::default(),
+            my_steam_id: None,
+            session_gap_minutes: 30,
+        }
+    }
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("tgm4-replay-manager").join("config.json"))
+    }
+
+    pub fn session_gap(&self) -> TimeDelta {
+        TimeDelta::minutes(self.session_gap_minutes as i64)
+    }
+
+    pub fn load() -> Self {
+        let mut config = Self::path()
+            .and_then(|path| fs::read(&path).ok().map(|bytes| (path, bytes)))
+            .map(|(path, bytes)| {
+                serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                    log::warn!("Ignoring invalid config {}: {e}", path.display());
+                    Self::default()
+                })
+            })
+            .unwrap_or_default();
+        if let Ok(api_key) = env::var("APIKEY") {
+            config.api_key = api_key;
+        }
+        config
+    }
+}
@@ -0,0 +1,198 @@
+//! Daily challenge replays, the ones with [`Mod::Daily`] set.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{NaiveDate, TimeDelta};
+
+use crate::{
+    replay::{Mod, Mode, Replay, Rule},
+    stats::compare,
+    steam_id::SteamId,
+};
+
+/// Everyone's Daily replays of one day and seed.
+#[derive(Debug)]
+pub struct Challenge<'a> {
+    pub day: NaiveDate,
+    pub seed: u32,
+    pub mode: Mode,
+    pub rule: Rule,
+    /// Each player's best attempt, best first.
+    pub ranking: Vec<&'a Replay>,
+    pub attempts: usize,
+    /// Other days with Daily replays of this seed, the same challenge played either side of
+    /// midnight or in another time zone.
+    pub same_seed_days: Vec<NaiveDate>,
+}
+
+impl Challenge<'_> {
+    /// 1-based, `None` when `id` didn't play it.
+    pub fn rank_of(&self, id: SteamId) -> Option<usize> {
+        self.ranking
+            .iter()
+            .position(|r| r.steamid == id)
+            .map(|i| i + 1)
+    }
+}
+
+/// Days played in a row, see [`streaks`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Streaks {
+    /// Ending today, or yesterday as today's challenge can still be played.
+    pub current: usize,
+    pub longest: usize,
+}
+
+fn day(replay: &Replay) -> NaiveDate {
+    replay.played_at.date_naive()
+}
+
+fn is_daily(replay: &Replay) -> bool {
+    replay.modifiers.contains(&Mod::Daily)
+}
+
+/// Daily replays grouped by local calendar day and seed, oldest first.
+pub fn challenges<'a>(replays: impl IntoIterator<Item = &'a Replay>) -> Vec<Challenge<'a>> {
+    let mut groups: BTreeMap<(NaiveDate, u32), Vec<&Replay>> = BTreeMap::new();
+    for replay in replays.into_iter().filter(|r| is_daily(r)) {
+        groups
+            .entry((day(replay), replay.seed))
+            .or_default()
+            .push(replay);
+    }
+    let mut days_by_seed: BTreeMap<u32, Vec<NaiveDate>> = BTreeMap::new();
+    for (day, seed) in groups.keys() {
+        days_by_seed.entry(*seed).or_default().push(*day);
+    }
+
+    groups
+        .into_iter()
+        .map(|((day, seed), replays)| {
+            let mut best: BTreeMap<SteamId, &Replay> = BTreeMap::new();
+            for replay in &replays {
+                best.entry(replay.steamid)
+                    .and_modify(|b| {
+                        if compare(replay, b).is_gt() {
+                            *b = replay;
+                        }
+                    })
+                    .or_insert(replay);
+            }
+            let mut ranking: Vec<&Replay> = best.into_values().collect();
+            ranking.sort_by(|a, b| compare(b, a));
+            Challenge {
+                day,
+                seed,
+                mode: replays[0].mode.group(),
+                rule: replays[0].rule,
+                attempts: replays.len(),
+                ranking,
+                same_seed_days: days_by_seed[&seed]
+                    .iter()
+                    .copied()
+                    .filter(|d| *d != day)
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+/// How many Daily replays `id` played each day.
+pub fn participation<'a>(
+    id: SteamId,
+    replays: impl IntoIterator<Item = &'a Replay>,
+) -> BTreeMap<NaiveDate, usize> {
+    let mut days = BTreeMap::new();
+    for replay in replays
+        .into_iter()
+        .filter(|r| r.steamid == id && is_daily(r))
+    {
+        *days.entry(day(replay)).or_default() += 1;
+    }
+    days
+}
+
+pub fn streaks(days: &BTreeSet<NaiveDate>, today: NaiveDate) -> Streaks {
+    let mut streaks = Streaks::default();
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for &day in days {
+        run = match previous {
+            Some(p) if day - p == TimeDelta::days(1) => run + 1,
+            _ => 1,
+        };
+        streaks.longest = streaks.longest.max(run);
+        previous = Some(day);
+    }
+    if previous.is_some_and(|last| today - last <= TimeDelta::days(1)) {
+        streaks.current = run;
+    }
+    streaks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::tests::replay;
+
+    fn daily(steamid: u32, level: u32, seed: u32, days: i64) -> Replay {
+        let mut replay = replay(Mode::Master, steamid, level, 0, 600);
+        replay.modifiers = vec![Mod::Daily];
+        replay.seed = seed;
+        replay.played_at += TimeDelta::days(days);
+        replay
+    }
+
+    #[test]
+    fn test_challenges() {
+        let replays = [
+            daily(1, 300, 10, 0),
+            daily(1, 500, 10, 0),
+            daily(2, 400, 10, 0),
+            daily(2, 200, 20, 1),
+            // same seed the next day, another time zone
+            daily(3, 999, 20, 2),
+            replay(Mode::Master, 1, 999, 0, 600),
+        ];
+        let challenges = challenges(&replays);
+        assert_eq!(challenges.len(), 3);
+
+        let first = &challenges[0];
+        assert_eq!(first.attempts, 3);
+        assert_eq!(
+            first.ranking.iter().map(|r| r.level).collect::<Vec<_>>(),
+            [500, 400]
+        );
+        assert_eq!(first.rank_of(SteamId::from_account_id(2)), Some(2));
+        assert_eq!(first.rank_of(SteamId::from_account_id(3)), None);
+        assert!(first.same_seed_days.is_empty());
+        assert_eq!(challenges[1].same_seed_days, [challenges[2].day]);
+        assert_eq!(challenges[2].same_seed_days, [challenges[1].day]);
+    }
+
+    #[test]
+    fn test_streaks() {
+        let replays = [
+            daily(1, 100, 1, 0),
+            daily(1, 100, 1, 0),
+            daily(1, 100, 2, 1),
+            daily(1, 100, 3, 2),
+            daily(1, 100, 5, 4),
+            daily(1, 100, 6, 5),
+            daily(2, 100, 7, 6),
+        ];
+        let days = participation(SteamId::from_account_id(1), &replays);
+        assert_eq!(days.values().copied().collect::<Vec<_>>(), [2, 1, 1, 1, 1]);
+
+        let days: BTreeSet<NaiveDate> = days.into_keys().collect();
+        let last = *days.last().unwrap();
+        let expected = |current| Streaks {
+            current,
+            longest: 3,
+        };
+        assert_eq!(streaks(&days, last), expected(2));
+        assert_eq!(streaks(&days, last + TimeDelta::days(1)), expected(2));
+        assert_eq!(streaks(&days, last + TimeDelta::days(2)), expected(0));
+        assert_eq!(streaks(&BTreeSet::new(), last), Streaks::default());
+    }
+}
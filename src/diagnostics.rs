@@ -0,0 +1,88 @@
+use std::{fmt, path::PathBuf};
+
+use chrono::{DateTime, Local};
+use log::Level;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DiagnosticKind {
+    /// A file or folder couldn't be read.
+    Io,
+    /// A replay couldn't be decoded.
+    Parse,
+    UnsupportedVersion,
+    /// The preamble's size or checksum doesn't match the file.
+    Corrupted,
+    UnknownModifier,
+    /// A request failed or returned an error status.
+    Http,
+    /// A response body wasn't what we expected.
+    Json,
+}
+
+impl fmt::Display for DiagnosticKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiagnosticKind::Io => write!(f, "I/O"),
+            DiagnosticKind::Parse => write!(f, "Parse"),
+            DiagnosticKind::UnsupportedVersion => write!(f, "Unsupported build"),
+            DiagnosticKind::Corrupted => write!(f, "Corrupted"),
+            DiagnosticKind::UnknownModifier => write!(f, "Unknown modifier"),
+            DiagnosticKind::Http => write!(f, "HTTP"),
+            DiagnosticKind::Json => write!(f, "JSON"),
+        }
+    }
+}
+
+/// Something that went wrong (or looks off) while loading, kept around to show in the log panel.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub level: Level,
+    pub kind: DiagnosticKind,
+    pub path: Option<PathBuf>,
+    pub message: String,
+    pub timestamp: DateTime<Local>,
+}
+
+#[derive(Debug, Default)]
+pub struct Diagnostics(Vec<Diagnostic>);
+
+impl Diagnostics {
+    pub fn error(&mut self, kind: DiagnosticKind, path: Option<PathBuf>, message: String) {
+        self.push(Level::Error, kind, path, message);
+    }
+
+    pub fn warn(&mut self, kind: DiagnosticKind, path: Option<PathBuf>, message: String) {
+        self.push(Level::Warn, kind, path, message);
+    }
+
+    /// Also forwards to the logger, so `RUST_LOG` still shows everything on stderr.
+    fn push(&mut self, level: Level, kind: DiagnosticKind, path: Option<PathBuf>, message: String) {
+        match &path {
+            Some(path) => log::log!(level, "{kind}: {}: {message}", path.display()),
+            None => log::log!(level, "{kind}: {message}"),
+        }
+        self.0.push(Diagnostic {
+            level,
+            kind,
+            path,
+            message,
+            timestamp: Local::now(),
+        });
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.0.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn count(&self, level: Level) -> usize {
+        self.0.iter().filter(|d| d.level == level).count()
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::HashSet;
+
+use serde::Deserialize;
+
+use crate::steam_id::SteamId;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    Me,
+    Friend,
+    Stranger,
+}
+
+/// Which players the replay tables show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum PlayerFilter {
+    #[default]
+    Everyone,
+    Me,
+    Friends,
+    /// Leaderboard replays downloaded from strangers.
+    Others,
+}
+
+impl PlayerFilter {
+    pub const ALL: [PlayerFilter; 4] = [
+        PlayerFilter::Everyone,
+        PlayerFilter::Me,
+        PlayerFilter::Friends,
+        PlayerFilter::Others,
+    ];
+
+    pub fn matches(self, relation: Relation) -> bool {
+        match self {
+            PlayerFilter::Everyone => true,
+            PlayerFilter::Me => relation == Relation::Me,
+            PlayerFilter::Friends => relation == Relation::Friend,
+            PlayerFilter::Others => relation == Relation::Stranger,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PlayerFilter::Everyone => "Everyone",
+            PlayerFilter::Me => "Me",
+            PlayerFilter::Friends => "Friends",
+            PlayerFilter::Others => "Everyone else",
+        }
+    }
+}
+
+/// Who "me" is and who their friends are, to tell our replays from downloaded ones.
+#[derive(Debug, Default)]
+pub struct Friends {
+    pub me: Option<SteamId>,
+    friends: HashSet<SteamId>,
+}
+
+impl Friends {
+    pub fn new(me: Option<SteamId>, friends: impl IntoIterator<Item = SteamId>) -> Self {
+        Self {
+            me,
+            friends: friends.into_iter().collect(),
+        }
+    }
+
+    pub fn relation(&self, id: SteamId) -> Relation {
+        if self.me == Some(id) {
+            Relation::Me
+        } else if self.friends.contains(&id) {
+            Relation::Friend
+        } else {
+            Relation::Stranger
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.friends.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.friends.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter() {
+        let [me, friend, stranger] = [1, 2, 3].map(SteamId::from_account_id);
+        let friends = Friends::new(Some(me), [friend, me]);
+        let shown = |filter: PlayerFilter| {
+            [me, friend, stranger]
+                .into_iter()
+                .filter(|id| filter.matches(friends.relation(*id)))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(shown(PlayerFilter::Everyone), [me, friend, stranger]);
+        assert_eq!(shown(PlayerFilter::Me), [me]);
+        assert_eq!(shown(PlayerFilter::Friends), [friend]);
+        assert_eq!(shown(PlayerFilter::Others), [stranger]);
+        assert_eq!(Friends::default().relation(me), Relation::Stranger);
+    }
+}
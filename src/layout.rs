@@ -0,0 +1,427 @@
+use std::fmt;
+
+use chrono::DateTime;
+
+use crate::replay::{Integrity, Mod, ReplayError};
+
+/// Every header field the parser knows about.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Field {
+    Magic,
+    /// Total size of the file in bytes.
+    Size,
+    /// CRC-32 of everything from [`Layout::checksum_start`] to the end of the file.
+    Checksum,
+    ShiranuiPoints,
+    SteamId,
+    Timestamp,
+    /// Konoha difficulty, or 0x03 for versus.
+    Alt,
+    Mode,
+    Rule,
+    OpponentRule,
+    Modifiers,
+    Seed,
+    Frames,
+    Level,
+    Score,
+    Bravo,
+    ShiranuiTier,
+    OpponentSeed,
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Field::Magic => "magic",
+            Field::Size => "size",
+            Field::Checksum => "checksum",
+            Field::ShiranuiPoints => "shiranui points",
+            Field::SteamId => "steam id",
+            Field::Timestamp => "timestamp",
+            Field::Alt => "alt byte",
+            Field::Mode => "mode",
+            Field::Rule => "rule",
+            Field::OpponentRule => "opponent rule",
+            Field::Modifiers => "modifiers",
+            Field::Seed => "seed",
+            Field::Frames => "frame count",
+            Field::Level => "level",
+            Field::Score => "score",
+            Field::Bravo => "bravo",
+            Field::ShiranuiTier => "shiranui tier",
+            Field::OpponentSeed => "opponent seed",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Endian {
+    Little,
+    Big,
+}
+
+/// How the raw integer of a field should be read by a human.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Decoding {
+    Integer,
+    /// Unix timestamp in seconds.
+    Timestamp,
+    /// Frame count at 60fps.
+    Frames,
+    Bitflags,
+    /// A small enumeration, matched by the parser.
+    Enumeration,
+    /// Raw bytes shown as text, like file magics.
+    Ascii,
+    /// Opaque value shown in hexadecimal, like checksums.
+    Hex,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct FieldSpec {
+    pub field: Field,
+    pub offset: usize,
+    pub width: usize,
+    pub endian: Endian,
+    pub decoding: Decoding,
+}
+
+impl FieldSpec {
+    const fn le(field: Field, offset: usize, width: usize, decoding: Decoding) -> Self {
+        Self {
+            field,
+            offset,
+            width,
+            endian: Endian::Little,
+            decoding,
+        }
+    }
+
+    pub fn range(&self) -> std::ops::Range<usize> {
+        self.offset..self.offset + self.width
+    }
+
+    pub fn describe(&self, value: u64) -> String {
+        match self.decoding {
+            Decoding::Integer => value.to_string(),
+            Decoding::Timestamp => DateTime::from_timestamp(value as i64, 0)
+                .map(|d| d.to_rfc3339())
+                .unwrap_or_else(|| format!("invalid ({value})")),
+            Decoding::Frames => format!("{value} frames"),
+            Decoding::Bitflags => format!("{value:#010b}"),
+            Decoding::Enumeration => format!("{value:#04X}"),
+            Decoding::Ascii => {
+                let mut bytes = value.to_le_bytes()[..self.width].to_vec();
+                if self.endian == Endian::Big {
+                    bytes.reverse();
+                }
+                bytes
+                    .iter()
+                    .map(|b| match b {
+                        0x20..=0x7E => *b as char,
+                        _ => '.',
+                    })
+                    .collect()
+            }
+            Decoding::Hex => format!("{value:#0width$X}", width = self.width * 2 + 2),
+        }
+    }
+}
+
+/// Where everything lives in a replay header, so a game update shifting offsets only
+/// needs a new table instead of parser changes.
+#[derive(Debug)]
+pub struct Layout {
+    pub name: &'static str,
+    pub fields: &'static [FieldSpec],
+    /// Bits of the [`Field::Modifiers`] byte, a modifier is set when all bits of its mask are.
+    pub modifier_bits: &'static [(u8, Mod)],
+    /// Values a field must hold for a header to be recognised as written by this build.
+    pub signature: &'static [(Field, &'static [u64])],
+    /// First byte covered by the [`Field::Checksum`], the preamble holding it is excluded.
+    pub checksum_start: usize,
+}
+
+pub const V1: Layout = Layout {
+    name: "1.0",
+    fields: &[
+        //TODO magic, size and checksum once decoded from real replays, the magic then goes in
+        // the signature. Until then nothing is checked and every replay is Unchecked
+        FieldSpec::le(Field::ShiranuiPoints, 0x0C, 1, Decoding::Integer),
+        FieldSpec::le(Field::SteamId, 0x10, 8, Decoding::Integer),
+        FieldSpec::le(Field::Timestamp, 0x18, 8, Decoding::Timestamp),
+        FieldSpec::le(Field::Alt, 0x20, 1, Decoding::Enumeration),
+        FieldSpec::le(Field::Mode, 0x24, 1, Decoding::Enumeration),
+        FieldSpec::le(Field::Rule, 0x28, 1, Decoding::Enumeration),
+        FieldSpec::le(Field::OpponentRule, 0x2C, 1, Decoding::Enumeration),
+        FieldSpec::le(Field::Modifiers, 0x30, 1, Decoding::Bitflags),
+        FieldSpec::le(Field::Seed, 0x34, 4, Decoding::Integer),
+        FieldSpec::le(Field::Frames, 0x38, 4, Decoding::Frames),
+        FieldSpec::le(Field::Level, 0x3C, 4, Decoding::Integer),
+        FieldSpec::le(Field::Score, 0x40, 4, Decoding::Integer),
+        FieldSpec::le(Field::Bravo, 0x44, 1, Decoding::Integer),
+        FieldSpec::le(Field::ShiranuiTier, 0x48, 1, Decoding::Integer),
+        FieldSpec::le(Field::OpponentSeed, 0x104, 4, Decoding::Integer),
+    ],
+    modifier_bits: &[
+        (0b01000000, Mod::Daily),
+        (0b00110000, Mod::Easy),
+        (0b00000010, Mod::Big),
+        (0b00000001, Mod::MaxG),
+    ],
+    signature: &[
+        (Field::Mode, &[0x00, 0x01, 0x03, 0x04, 0x05]),
+        (Field::Rule, &[0x00, 0x01]),
+        (Field::OpponentRule, &[0x00, 0x01]),
+    ],
+    checksum_start: 0,
+};
+
+/// The layout used when nothing else is specified.
+pub const CURRENT: &Layout = &V1;
+
+/// Every known layout, newest first.
+pub const LAYOUTS: &[&Layout] = &[&V1];
+
+/// Finds the layout of the build that wrote this replay. A file too short for every
+/// signature is truncated, one that's long enough but matches none is from an unknown build.
+pub fn detect(bytes: &[u8]) -> Result<&'static Layout, ReplayError> {
+    LAYOUTS
+        .iter()
+        .copied()
+        .find(|layout| layout.matches(bytes))
+        .ok_or_else(|| {
+            let short: Vec<Field> = LAYOUTS
+                .iter()
+                .filter_map(|layout| layout.truncated_signature(bytes))
+                .collect();
+            if short.len() == LAYOUTS.len() {
+                ReplayError::Truncated(short[0])
+            } else {
+                ReplayError::UnsupportedVersion
+            }
+        })
+}
+
+impl Layout {
+    pub fn spec(&self, field: Field) -> Option<&FieldSpec> {
+        self.fields.iter().find(|spec| spec.field == field)
+    }
+
+    /// Reads a field as an unsigned integer, fields are at most 8 bytes wide.
+    pub fn read(&self, bytes: &[u8], field: Field) -> Result<u64, ReplayError> {
+        let spec = self.spec(field).ok_or(ReplayError::MissingField(field))?;
+        let raw = bytes
+            .get(spec.range())
+            .ok_or(ReplayError::Truncated(field))?;
+        let fold = |acc: u64, byte: &u8| (acc << 8) | *byte as u64;
+        Ok(match spec.endian {
+            Endian::Little => raw.iter().rev().fold(0, fold),
+            Endian::Big => raw.iter().fold(0, fold),
+        })
+    }
+
+    pub fn read_u8(&self, bytes: &[u8], field: Field) -> Result<u8, ReplayError> {
+        self.read(bytes, field).map(|v| v as u8)
+    }
+
+    pub fn read_u32(&self, bytes: &[u8], field: Field) -> Result<u32, ReplayError> {
+        self.read(bytes, field).map(|v| v as u32)
+    }
+
+    /// Whether the signature fields fit and match, the rest may still be truncated.
+    pub fn matches(&self, bytes: &[u8]) -> bool {
+        self.signature.iter().all(|(field, values)| {
+            self.read(bytes, *field)
+                .is_ok_and(|value| values.contains(&value))
+        })
+    }
+
+    /// The first signature field `bytes` is too short to hold.
+    fn truncated_signature(&self, bytes: &[u8]) -> Option<Field> {
+        self.signature
+            .iter()
+            .map(|(field, _)| *field)
+            .find(|field| matches!(self.read(bytes, *field), Err(ReplayError::Truncated(_))))
+    }
+
+    /// Checks the size and checksum from the preamble against the file, when the layout has them.
+    pub fn integrity(&self, bytes: &[u8]) -> Integrity {
+        if let Ok(size) = self.read(bytes, Field::Size)
+            && size != bytes.len() as u64
+        {
+            return Integrity::SizeMismatch {
+                expected: size,
+                actual: bytes.len(),
+            };
+        }
+        let Ok(checksum) = self.read(bytes, Field::Checksum) else {
+            return Integrity::Unchecked;
+        };
+        let actual = crc32(bytes.get(self.checksum_start..).unwrap_or_default());
+        if checksum as u32 != actual {
+            return Integrity::ChecksumMismatch {
+                expected: checksum as u32,
+                actual,
+            };
+        }
+        Integrity::Intact
+    }
+
+    /// Whether any known field covers this header offset.
+    pub fn claims(&self, offset: usize) -> bool {
+        self.fields
+            .iter()
+            .any(|spec| spec.range().contains(&offset))
+    }
+
+    /// Length of the header up to the end of the furthest known field.
+    pub fn header_len(&self) -> usize {
+        self.fields
+            .iter()
+            .map(|spec| spec.range().end)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// CRC-32 (IEEE 802.3, reflected, as used by zip).
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFFFFFFu32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB88320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BIG: Layout = Layout {
+        name: "test",
+        fields: &[FieldSpec {
+            field: Field::Score,
+            offset: 1,
+            width: 2,
+            endian: Endian::Big,
+            decoding: Decoding::Integer,
+        }],
+        modifier_bits: &[],
+        signature: &[],
+        checksum_start: 0,
+    };
+
+    /// A made up preamble, no build is known to write one like it.
+    const PREAMBLE: Layout = Layout {
+        name: "test preamble",
+        fields: &[
+            FieldSpec::le(Field::Magic, 0x00, 4, Decoding::Ascii),
+            FieldSpec::le(Field::Size, 0x04, 4, Decoding::Integer),
+            FieldSpec::le(Field::Checksum, 0x08, 4, Decoding::Hex),
+        ],
+        modifier_bits: &[],
+        signature: &[],
+        checksum_start: 0x10,
+    };
+
+    #[test]
+    fn test_read() {
+        let mut bytes = vec![0u8; V1.header_len()];
+        bytes[0x3C..0x40].copy_from_slice(&123456u32.to_le_bytes());
+        assert_eq!(V1.read(&bytes, Field::Level).unwrap(), 123456);
+        assert_eq!(BIG.read(&[0xFF, 0x01, 0x02], Field::Score).unwrap(), 0x0102);
+    }
+
+    #[test]
+    fn test_read_errors() {
+        assert!(matches!(
+            V1.read(&[0; 0x20], Field::Mode),
+            Err(ReplayError::Truncated(Field::Mode))
+        ));
+        assert!(matches!(
+            BIG.read(&[0; 3], Field::Level),
+            Err(ReplayError::MissingField(Field::Level))
+        ));
+    }
+
+    #[test]
+    fn test_detect() {
+        let mut bytes = vec![0u8; V1.header_len()];
+        assert_eq!(detect(&bytes).map(|l| l.name).ok(), Some(V1.name));
+        // only the signature has to fit
+        assert_eq!(detect(&bytes[..0x30]).map(|l| l.name).ok(), Some(V1.name));
+        assert!(matches!(
+            detect(&bytes[..0x20]),
+            Err(ReplayError::Truncated(Field::Mode))
+        ));
+        bytes[0x24] = 0x02;
+        assert!(matches!(
+            detect(&bytes),
+            Err(ReplayError::UnsupportedVersion)
+        ));
+        bytes[0x24] = 0x05;
+        bytes[0x28] = 0x07;
+        assert!(matches!(
+            detect(&bytes),
+            Err(ReplayError::UnsupportedVersion)
+        ));
+    }
+
+    #[test]
+    fn test_integrity() {
+        assert_eq!(crc32(b"123456789"), 0xCBF43926);
+
+        let mut bytes = vec![0u8; 0x200];
+        bytes[0x150] = 0x42;
+        bytes[0x04..0x08].copy_from_slice(&0x200u32.to_le_bytes());
+        let checksum = crc32(&bytes[0x10..]);
+        bytes[0x08..0x0C].copy_from_slice(&checksum.to_le_bytes());
+        assert_eq!(PREAMBLE.integrity(&bytes), Integrity::Intact);
+
+        bytes[0x150] = 0x43;
+        assert!(matches!(
+            PREAMBLE.integrity(&bytes),
+            Integrity::ChecksumMismatch { expected, .. } if expected == checksum
+        ));
+        assert_eq!(
+            PREAMBLE.integrity(&bytes[..0x1FF]),
+            Integrity::SizeMismatch {
+                expected: 0x200,
+                actual: 0x1FF
+            }
+        );
+        assert_eq!(BIG.integrity(&bytes), Integrity::Unchecked);
+        // the preamble isn't located in real replays yet, so none get flagged
+        assert_eq!(V1.integrity(&bytes), Integrity::Unchecked);
+    }
+
+    #[test]
+    fn test_describe() {
+        let magic = PREAMBLE.spec(Field::Magic).unwrap();
+        assert_eq!(magic.describe(u32::from_le_bytes(*b"RPL\0") as u64), "RPL.");
+        let checksum = PREAMBLE.spec(Field::Checksum).unwrap();
+        assert_eq!(checksum.describe(0xBEEF), "0x0000BEEF");
+    }
+
+    #[test]
+    fn test_claims() {
+        assert_eq!(V1.header_len(), 0x108);
+        assert!(V1.claims(0x10));
+        assert!(V1.claims(0x17));
+        assert!(!V1.claims(0x00));
+        assert!(!V1.claims(0x0B));
+        assert!(V1.claims(0x0C));
+        assert!(!V1.claims(0x0D));
+        assert!(!V1.claims(0x108));
+    }
+}
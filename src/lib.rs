@@ -1 +1,19 @@
+#![warn(clippy::all, rust_2018_idioms)]
 
+pub mod analysis;
+pub mod charts;
+pub mod cli;
+pub mod config;
+pub mod daily;
+pub mod diagnostics;
+pub mod friends;
+pub mod layout;
+pub mod name_cache;
+pub mod names;
+pub mod replay;
+pub mod sim;
+pub mod stats;
+pub mod steam;
+pub mod steam_id;
+pub mod ui;
+pub mod vdf;
@@ -0,0 +1,233 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::steam_id::SteamId;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedName {
+    pub name: String,
+    pub fetched_at: DateTime<Utc>,
+    /// Every persona the Web API returned for this id, oldest first. Switching back to an
+    /// older name starts a new entry.
+    #[serde(default)]
+    pub history: Vec<Persona>,
+    #[serde(default)]
+    pub avatars: Option<AvatarUrls>,
+}
+
+/// The 32px and 64px avatars from `GetPlayerSummaries`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AvatarUrls {
+    pub small: String,
+    pub medium: String,
+}
+
+/// A persona name and the first and last fetch that returned it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Persona {
+    pub name: String,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+impl CachedName {
+    /// The persona the player most likely had at `when`: the last one seen before it,
+    /// or the first one ever seen if `when` is older than the whole history.
+    pub fn persona_at(&self, when: DateTime<Utc>) -> Option<&Persona> {
+        self.history
+            .iter()
+            .filter(|p| p.first_seen <= when)
+            .max_by_key(|p| p.first_seen)
+            .or_else(|| self.history.iter().min_by_key(|p| p.first_seen))
+    }
+
+    /// The name of [`Self::persona_at`].
+    pub fn name_at(&self, when: DateTime<Utc>) -> Option<&str> {
+        self.persona_at(when).map(|p| p.name.as_str())
+    }
+}
+
+/// Persona names from previous runs, so only new or stale ids hit the Web API
+/// and names keep showing while offline.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct NameCache {
+    entries: HashMap<SteamId, CachedName>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl NameCache {
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("tgm4-replay-manager").join("names.json"))
+    }
+
+    /// A missing or unreadable file gives an empty cache that will be written to `path`.
+    pub fn load(path: PathBuf) -> Self {
+        let mut cache = match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                log::warn!("Ignoring corrupted name cache {}: {e}", path.display());
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        };
+        cache.path = Some(path);
+        cache
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_vec_pretty(self)?)
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn get(&self, id: SteamId) -> Option<&CachedName> {
+        self.entries.get(&id)
+    }
+
+    /// Records a fetched persona, extending the latest history entry if the name didn't change.
+    pub fn insert(&mut self, id: SteamId, name: String, fetched_at: DateTime<Utc>) {
+        let entry = self.entries.entry(id).or_insert_with(|| CachedName {
+            name: name.clone(),
+            fetched_at,
+            history: Vec::new(),
+            avatars: None,
+        });
+        match entry.history.last_mut().filter(|p| p.name == name) {
+            Some(persona) => persona.last_seen = persona.last_seen.max(fetched_at),
+            None => entry.history.push(Persona {
+                name: name.clone(),
+                first_seen: fetched_at,
+                last_seen: fetched_at,
+            }),
+        }
+        entry.name = name;
+        entry.fetched_at = fetched_at;
+    }
+
+    /// Records that the Web API didn't return the id, without forgetting its history.
+    pub fn insert_missing(&mut self, id: SteamId, placeholder: &str, fetched_at: DateTime<Utc>) {
+        let entry = self.entries.entry(id).or_insert_with(|| CachedName {
+            name: String::new(),
+            fetched_at,
+            history: Vec::new(),
+            avatars: None,
+        });
+        entry.name = placeholder.to_string();
+        entry.fetched_at = fetched_at;
+    }
+
+    pub fn set_avatars(&mut self, id: SteamId, avatars: AvatarUrls) {
+        if let Some(entry) = self.entries.get_mut(&id) {
+            entry.avatars = Some(avatars);
+        }
+    }
+
+    /// Ids that were never fetched or were fetched longer than `ttl` ago.
+    pub fn stale_ids(&self, ids: &[SteamId], ttl: TimeDelta, now: DateTime<Utc>) -> Vec<SteamId> {
+        ids.iter()
+            .filter(|id| {
+                self.entries
+                    .get(id)
+                    .is_none_or(|entry| now - entry.fetched_at > ttl)
+            })
+            .copied()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stale_ids() {
+        let now = Utc::now();
+        let ids = [1, 2, 3].map(SteamId::from);
+        let mut cache = NameCache::default();
+        cache.insert(ids[0], String::from("fresh"), now - TimeDelta::hours(1));
+        cache.insert(ids[1], String::from("stale"), now - TimeDelta::days(8));
+        assert_eq!(cache.stale_ids(&ids, TimeDelta::days(7), now), ids[1..]);
+        assert_eq!(cache.stale_ids(&ids[..1], TimeDelta::zero(), now), ids[..1]);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let path = std::env::temp_dir()
+            .join(format!("tgm4-name-cache-{}", std::process::id()))
+            .join("names.json");
+        let now = Utc::now();
+        let robin = SteamId::from(76561197960435530);
+        let mut cache = NameCache::load(path.clone());
+        assert!(cache.get(robin).is_none());
+        cache.insert(robin, String::from("Robin"), now);
+        cache.save().unwrap();
+
+        let cache = NameCache::load(path.clone());
+        let entry = cache.get(robin).unwrap();
+        assert_eq!(entry.name, "Robin");
+        assert_eq!(entry.fetched_at, now);
+
+        fs::write(&path, b"not json").unwrap();
+        assert!(NameCache::load(path.clone()).get(robin).is_none());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_history() {
+        let day = |d: i64| DateTime::<Utc>::from_timestamp(d * 86400, 0).unwrap();
+        let id = SteamId::from(1);
+        let mut cache = NameCache::default();
+        cache.insert(id, String::from("Robin"), day(10));
+        cache.insert(id, String::from("Robin"), day(12));
+        cache.insert(id, String::from("RW"), day(20));
+        cache.insert_missing(id, "Unknown/Spoofed", day(30));
+        cache.insert(id, String::from("Robin"), day(40));
+
+        let entry = cache.get(id).unwrap();
+        assert_eq!(entry.name, "Robin");
+        assert_eq!(
+            entry.history,
+            vec![
+                Persona {
+                    name: String::from("Robin"),
+                    first_seen: day(10),
+                    last_seen: day(12),
+                },
+                Persona {
+                    name: String::from("RW"),
+                    first_seen: day(20),
+                    last_seen: day(20),
+                },
+                Persona {
+                    name: String::from("Robin"),
+                    first_seen: day(40),
+                    last_seen: day(40),
+                },
+            ]
+        );
+        assert_eq!(entry.name_at(day(1)), Some("Robin"));
+        assert_eq!(entry.name_at(day(11)), Some("Robin"));
+        assert_eq!(entry.name_at(day(25)), Some("RW"));
+        assert_eq!(entry.name_at(day(35)), Some("RW"));
+        // switched back to the old name
+        assert_eq!(entry.name_at(day(40)), Some("Robin"));
+        assert_eq!(entry.name_at(day(50)), Some("Robin"));
+
+        cache.insert_missing(SteamId::from(2), "Unknown/Spoofed", day(30));
+        assert_eq!(cache.get(SteamId::from(2)).unwrap().name_at(day(30)), None);
+    }
+}
@@ -0,0 +1,374 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use glob::glob;
+
+use crate::{
+    diagnostics::{DiagnosticKind, Diagnostics},
+    steam::SteamApi,
+    steam_id::SteamId,
+    vdf,
+};
+
+/// Something that can turn a Steam ID into a display name.
+pub trait NameResolver {
+    /// `None` when this backend doesn't know the id, so the next one gets asked.
+    fn resolve(&self, id: SteamId) -> Option<&str>;
+}
+
+impl NameResolver for SteamApi {
+    fn resolve(&self, id: SteamId) -> Option<&str> {
+        self.name(id)
+    }
+}
+
+/// Names the Steam client on this machine knows, which works without a Web API key:
+/// accounts that logged in here and their friends.
+#[derive(Debug, Default)]
+pub struct LocalSteam {
+    names: HashMap<SteamId, String>,
+    /// Accounts each local user's client has cached, which are mostly their friends.
+    friends: HashMap<SteamId, Vec<SteamId>>,
+    /// The account that logged in last.
+    most_recent: Option<SteamId>,
+}
+
+impl LocalSteam {
+    /// Where the Steam client usually lives, the config can override it.
+    pub fn default_root() -> Option<PathBuf> {
+        #[cfg(unix)]
+        let candidates = {
+            let home = dirs::home_dir()?;
+            [
+                home.join(".steam/steam"),
+                home.join(".local/share/Steam"),
+                home.join(".var/app/com.valvesoftware.Steam/.local/share/Steam"),
+            ]
+        };
+        #[cfg(windows)]
+        let candidates = [
+            PathBuf::from(r"C:\Program Files (x86)\Steam"),
+            PathBuf::from(r"C:\Program Files\Steam"),
+        ];
+        candidates.into_iter().find(|p| p.join("config").is_dir())
+    }
+
+    pub fn load(steam_root: &Path) -> Self {
+        let mut local = Self::default();
+        if let Ok(text) = fs::read_to_string(steam_root.join("config").join("loginusers.vdf")) {
+            local.read_login_users(&text);
+        }
+        let pattern = steam_root
+            .join("userdata")
+            .join("*")
+            .join("config")
+            .join("localconfig.vdf");
+        for path in glob(&pattern.to_string_lossy())
+            .into_iter()
+            .flatten()
+            .flatten()
+        {
+            let account_id = path
+                .ancestors()
+                .nth(2)
+                .and_then(|dir| dir.file_name())
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.parse::<u32>().ok());
+            if let (Some(account_id), Ok(text)) = (account_id, fs::read_to_string(&path)) {
+                local.read_local_config(account_id, &text);
+            }
+        }
+        local
+    }
+
+    pub fn most_recent_user(&self) -> Option<SteamId> {
+        self.most_recent
+    }
+
+    /// Friends of a local account, from its client's cache.
+    pub fn friends_of(&self, owner: SteamId) -> &[SteamId] {
+        self.friends
+            .get(&owner)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    fn read_login_users(&mut self, text: &str) {
+        let Some(users) = vdf::parse(text) else {
+            log::warn!("Couldn't parse loginusers.vdf");
+            return;
+        };
+        for (id, user) in users.get("users").map(|u| u.entries()).unwrap_or_default() {
+            let Ok(id) = id.parse::<u64>().map(SteamId::from) else {
+                continue;
+            };
+            if let Some(name) = user.get("PersonaName").and_then(|n| n.as_str()) {
+                self.names.insert(id, name.to_string());
+            }
+            if user.get("MostRecent").and_then(|m| m.as_str()) == Some("1") {
+                self.most_recent = Some(id);
+            }
+        }
+    }
+
+    /// Friends are keyed by account id, the owner's own name sits next to them.
+    fn read_local_config(&mut self, account_id: u32, text: &str) {
+        let owner = SteamId::from_account_id(account_id);
+        let Some(config) = vdf::parse(text) else {
+            log::warn!("Couldn't parse localconfig.vdf of account {account_id}");
+            return;
+        };
+        let Some(friends) = config.get_path(&["UserLocalConfigStore", "friends"]) else {
+            return;
+        };
+        for (key, value) in friends.entries() {
+            if key.eq_ignore_ascii_case("PersonaName") {
+                if let Some(name) = value.as_str() {
+                    self.names.entry(owner).or_insert_with(|| name.to_string());
+                }
+            } else if let Ok(friend) = key.parse::<u32>().map(SteamId::from_account_id) {
+                self.friends.entry(owner).or_default().push(friend);
+                if let Some(name) = value.get("name").and_then(|n| n.as_str()) {
+                    self.names.entry(friend).or_insert_with(|| name.to_string());
+                }
+            }
+        }
+    }
+}
+
+impl NameResolver for LocalSteam {
+    fn resolve(&self, id: SteamId) -> Option<&str> {
+        self.names.get(&id).map(String::as_str)
+    }
+}
+
+/// Names the user picked, from a text file with one `steamid = name` per line,
+/// the id in any format [`SteamId`] parses.
+#[derive(Debug, Default)]
+pub struct Aliases {
+    aliases: HashMap<SteamId, String>,
+    path: Option<PathBuf>,
+    pub diagnostics: Diagnostics,
+}
+
+impl Aliases {
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("tgm4-replay-manager").join("aliases.txt"))
+    }
+
+    /// A missing file gives no aliases, edits will create it at `path`.
+    pub fn load(path: PathBuf) -> Self {
+        let mut aliases = fs::read_to_string(&path)
+            .map(|text| Self::parse(&text))
+            .unwrap_or_default();
+        aliases.path = Some(path);
+        aliases
+    }
+
+    /// Blank lines and `#` comments are skipped, so are lines that don't parse.
+    pub fn parse(text: &str) -> Self {
+        let aliases = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let (id, name) = line.split_once('=')?;
+                let name = name.trim();
+                (!name.is_empty()).then(|| Some((id.trim().parse().ok()?, name.to_string())))?
+            })
+            .collect();
+        Self {
+            aliases,
+            ..Default::default()
+        }
+    }
+
+    /// Rewrites the whole file sorted by id, comments added by hand don't survive.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut ids: Vec<_> = self.aliases.keys().collect();
+        ids.sort();
+        let mut text = String::from("# steamid = name\n");
+        for id in ids {
+            text.push_str(&format!("{id} = {}\n", self.aliases[id]));
+        }
+        fs::write(path, text)
+    }
+
+    /// Sets or, with an empty name, removes an alias and saves right away.
+    pub fn set(&mut self, id: SteamId, name: &str) {
+        let name = name.trim();
+        if name.is_empty() {
+            self.aliases.remove(&id);
+        } else {
+            self.aliases.insert(id, name.to_string());
+        }
+        if let Err(e) = self.save() {
+            self.diagnostics.error(
+                DiagnosticKind::Io,
+                self.path.clone(),
+                format!("Saving the aliases: {e}"),
+            );
+        }
+    }
+}
+
+impl NameResolver for Aliases {
+    fn resolve(&self, id: SteamId) -> Option<&str> {
+        self.aliases.get(&id).map(String::as_str)
+    }
+}
+
+/// Every name backend, asked in priority order: what the user set, then what
+/// this machine's Steam client knows, then the Web API.
+#[derive(Default)]
+pub struct NameChain {
+    pub aliases: Aliases,
+    pub local: LocalSteam,
+    pub steam_api: SteamApi,
+}
+
+impl NameChain {
+    fn resolvers(&self) -> [&dyn NameResolver; 3] {
+        [&self.aliases, &self.local, &self.steam_api]
+    }
+
+    pub fn get(&self, id: SteamId) -> &str {
+        //TODO let's figure out how to handle that later
+        self.resolvers()
+            .into_iter()
+            .find_map(|r| r.resolve(id))
+            .unwrap_or("Unknown/Unparsed")
+    }
+
+    /// The name Steam knows the player by, ignoring aliases.
+    pub fn persona(&self, id: SteamId) -> &str {
+        self.resolvers()[1..]
+            .iter()
+            .find_map(|r| r.resolve(id))
+            .unwrap_or("Unknown/Unparsed")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOGIN_USERS: &str = r#"
+"users"
+{
+	"76561198001860904"
+	{
+		"AccountName"		"tetra"
+		"PersonaName"		"Tetra Master"
+		"RememberPassword"		"1"
+		"MostRecent"		"1"
+	}
+}
+"#;
+
+    const LOCAL_CONFIG: &str = r#"
+"UserLocalConfigStore"
+{
+	"friends"
+	{
+		"PersonaName"		"Tetra (local)"
+		"170169802"
+		{
+			"name"		"Robin"
+			"NameHistory"
+			{
+				"0"		"Robin"
+			}
+		}
+		"communitypreferences"		"00"
+	}
+}
+"#;
+
+    #[test]
+    fn test_local_steam() {
+        let root = std::env::temp_dir().join(format!("tgm4-local-steam-{}", std::process::id()));
+        let config = root.join("userdata").join("41595176").join("config");
+        fs::create_dir_all(&config).unwrap();
+        fs::create_dir_all(root.join("config")).unwrap();
+        fs::write(root.join("config").join("loginusers.vdf"), LOGIN_USERS).unwrap();
+        fs::write(config.join("localconfig.vdf"), LOCAL_CONFIG).unwrap();
+
+        let local = LocalSteam::load(&root);
+        let resolve = |id: u64| local.resolve(SteamId::from(id));
+        assert_eq!(resolve(76561198001860904), Some("Tetra Master"));
+        assert_eq!(resolve(76561198130435530), Some("Robin"));
+        assert_eq!(resolve(76561197960435530), None);
+        assert_eq!(
+            local.most_recent_user(),
+            Some(SteamId::from(76561198001860904))
+        );
+        assert_eq!(
+            local.friends_of(SteamId::from_account_id(41595176)),
+            [SteamId::from(76561198130435530)]
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_aliases() {
+        let aliases = Aliases::parse(
+            "# steamid = name\n76561198001860904 = Tetra = GM\n\nnot an id = x\n76561197960435530=\n[U:1:1]=Robin\n",
+        );
+        assert_eq!(
+            aliases.resolve(SteamId::from(76561198001860904)),
+            Some("Tetra = GM")
+        );
+        assert_eq!(aliases.resolve(SteamId::from(76561197960435530)), None);
+        assert_eq!(aliases.resolve(SteamId::from_account_id(1)), Some("Robin"));
+        assert_eq!(aliases.aliases.len(), 2);
+    }
+
+    #[test]
+    fn test_priority() {
+        let [one, two, three] = [1, 2, 3].map(SteamId::from_account_id);
+        let mut chain = NameChain {
+            aliases: Aliases::parse("1 = Alias"),
+            ..Default::default()
+        };
+        chain.local.names.insert(one, String::from("Local"));
+        chain.local.names.insert(two, String::from("Local"));
+        assert_eq!(chain.get(one), "Alias");
+        assert_eq!(chain.get(two), "Local");
+        assert_eq!(chain.get(three), "Unknown/Unparsed");
+        assert_eq!(chain.get(SteamId::from(3)), "Unknown/Spoofed");
+        assert_eq!(chain.persona(one), "Local");
+    }
+
+    #[test]
+    fn test_alias_roundtrip() {
+        let path = std::env::temp_dir()
+            .join(format!("tgm4-aliases-{}", std::process::id()))
+            .join("aliases.txt");
+        let [one, two, three] = [1, 2, 3].map(SteamId::from_account_id);
+        let mut aliases = Aliases::load(path.clone());
+        aliases.set(two, " GM now ");
+        aliases.set(one, "Robin");
+        aliases.set(three, "Tetra");
+        aliases.set(three, "");
+        assert!(aliases.diagnostics.is_empty());
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# steamid = name\n76561197960265729 = Robin\n76561197960265730 = GM now\n"
+        );
+
+        let aliases = Aliases::load(path.clone());
+        assert_eq!(aliases.resolve(two), Some("GM now"));
+        assert_eq!(aliases.resolve(three), None);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
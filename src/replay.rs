@@ -1,16 +1,20 @@
-use chrono::{DateTime, Local, TimeZone, Utc};
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Local};
+use serde::Deserialize;
 use std::{
-    array::TryFromSliceError,
     collections::HashSet,
     fmt::{self},
-    time::Duration,
 };
 
 use glob::glob;
 use std::path::PathBuf;
 
-#[derive(Debug, Deserialize, PartialEq, Eq, Clone, Copy)]
+use crate::{
+    diagnostics::{DiagnosticKind, Diagnostics},
+    layout::{self, Field, Layout},
+    steam_id::SteamId,
+};
+
+#[derive(Debug, Deserialize, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum Mode {
     Marathon,
     Master,
@@ -21,7 +25,31 @@ pub enum Mode {
     Versus, //TODO parse versus stuff (garbage type etc)
 }
 
-#[derive(Debug, PartialEq, Eq, Deserialize, Clone, Copy)]
+impl Mode {
+    /// Shiranui's tier and points are progress within the mode, not a separate mode.
+    pub fn group(self) -> Mode {
+        match self {
+            Mode::Shiranui(_, _) => Mode::Shiranui(0, 0),
+            mode => mode,
+        }
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mode::Marathon => write!(f, "Marathon"),
+            Mode::Master => write!(f, "Master"),
+            Mode::Normal => write!(f, "Normal"),
+            Mode::Konoha(difficulty) => write!(f, "Konoha ({difficulty:?})"),
+            Mode::Shiranui(_, _) => write!(f, "Shiranui"),
+            Mode::Asuka => write!(f, "Asuka"),
+            Mode::Versus => write!(f, "Versus"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Clone, Copy)]
 pub enum Rule {
     Standard = 0,
     Tgm = 1,
@@ -45,15 +73,29 @@ impl From<u8> for Rule {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Deserialize, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, Deserialize, Clone, Copy)]
 pub enum Mod {
     MaxG,
     Daily,
     Easy,
     Big,
+    /// Bits of the modifier byte no known modifier accounts for.
+    Unknown(u8),
 }
 
-#[derive(Debug, Deserialize, PartialEq, Eq, Clone, Copy)]
+impl fmt::Display for Mod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mod::MaxG => write!(f, "20G"),
+            Mod::Daily => write!(f, "Daily"),
+            Mod::Easy => write!(f, "Easy"),
+            Mod::Big => write!(f, "Big"),
+            Mod::Unknown(bits) => write!(f, "Unknown({bits:#010b})"),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum KonohaDifficulty {
     Easy,
     Hard,
@@ -68,19 +110,43 @@ impl From<u8> for KonohaDifficulty {
     }
 }
 
+/// In-game time as the raw frame count, the game runs at 60fps.
+#[derive(Debug, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Default)]
+pub struct GameTime(pub u32);
+
+impl GameTime {
+    pub const FPS: u64 = 60;
+
+    /// Hundredths of a second, rounded down like the in-game timer.
+    pub fn as_centis(&self) -> u64 {
+        self.0 as u64 * 100 / Self::FPS
+    }
+}
+
+/// `mm'ss"cc`, minutes keep growing past an hour.
+impl fmt::Display for GameTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let centis = self.as_centis();
+        let (mins, secs, centis) = (centis / 6000, centis / 100 % 60, centis % 100);
+        write!(f, "{mins:02}'{secs:02}\"{centis:02}")
+    }
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct Replay {
     pub mode: Mode,
     pub rule: Rule,
-    pub steamid: u64,
+    pub steamid: SteamId,
     pub played_at: DateTime<Local>,
     pub modifiers: Vec<Mod>,
+    pub modifier_byte: u8,
     pub score: u32,
     pub seed: u32,
-    pub time: Duration,
+    pub time: GameTime,
     pub level: u32,
     pub bravo: u8,
     pub opponent: Option<Opponent>,
+    pub integrity: Integrity,
     // skin
     // version?
     // TODO diagonals?
@@ -88,48 +154,79 @@ pub struct Replay {
 
 #[derive(Debug, Deserialize, Clone, Copy)]
 pub struct Opponent {
-    seed: u32,
-    rule: Rule,
+    pub seed: u32,
+    pub rule: Rule,
     // skin
     // bravo?
 }
 
+/// Result of checking the preamble's size and checksum against the file.
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone, Copy)]
+pub enum Integrity {
+    Intact,
+    SizeMismatch {
+        expected: u64,
+        actual: usize,
+    },
+    ChecksumMismatch {
+        expected: u32,
+        actual: u32,
+    },
+    /// The layout doesn't know where the checksum is.
+    Unchecked,
+}
+
+impl fmt::Display for Integrity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Integrity::Intact => write!(f, "Intact"),
+            Integrity::SizeMismatch { expected, actual } => {
+                write!(f, "File is {actual} bytes, header says {expected}")
+            }
+            Integrity::ChecksumMismatch { expected, actual } => {
+                write!(f, "Checksum is {actual:08X}, header says {expected:08X}")
+            }
+            Integrity::Unchecked => write!(f, "Unchecked, no checksum known for this build"),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum ReplayError {
-    Slice(TryFromSliceError),
-    Parse,
+    /// The file ends before this field.
+    Truncated(Field),
+    /// The layout has no offset for this field.
+    MissingField(Field),
+    /// No known layout matches, the replay was written by a build we can't decode.
+    UnsupportedVersion,
+    /// The field holds a value that means nothing we know of.
+    Parse(Field),
 }
 
 //TODO improve
 impl fmt::Display for ReplayError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
-            ReplayError::Slice(e) => e.fmt(f),
-            ReplayError::Parse => write!(f, "Error parsing the modifier"),
+            ReplayError::Truncated(field) => write!(f, "File too short to contain the {field}"),
+            ReplayError::MissingField(field) => write!(f, "Layout has no {field} field"),
+            ReplayError::UnsupportedVersion => write!(f, "Replay from an unsupported build"),
+            ReplayError::Parse(field) => write!(f, "Invalid {field}"),
         }
     }
 }
 
-fn parse_modifier(byte: u8) -> Vec<Mod> {
+fn parse_modifier(byte: u8, layout: &Layout) -> Vec<Mod> {
     let mut r = Vec::with_capacity(6);
-    if byte & 0b01000000 == 0b01000000 {
-        r.push(Mod::Daily);
-    }
-
-    if byte & 0b00110000 == 0b00110000 {
-        r.push(Mod::Easy);
-    }
-
-    if byte & 0b00001100 == 0b00001100 {
-        eprintln!("This shouldn't be used this is epic !!!");
-    }
-
-    if byte & 0b00000010 == 0b00000010 {
-        r.push(Mod::Big);
+    let mut known = 0;
+    for (mask, modifier) in layout.modifier_bits {
+        if byte & mask == *mask {
+            r.push(*modifier);
+            known |= mask;
+        }
     }
 
-    if byte & 0b00000001 == 0b00000001 {
-        r.push(Mod::MaxG);
+    if byte & !known != 0 {
+        r.push(Mod::Unknown(byte & !known));
     }
 
     r
@@ -157,22 +254,27 @@ fn parse_mode(
 //TODO test garbage type in replay?
 impl Replay {
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReplayError> {
-        let steam_id_bytes = &bytes[0x10..0x18].try_into().map_err(ReplayError::Slice)?;
+        let layout = layout::detect(bytes)?;
+        Self::from_bytes_with(bytes, layout)
+    }
 
-        let timestamp_bytes = &bytes[0x18..0x20].try_into().map_err(ReplayError::Slice)?;
+    pub fn from_bytes_with(bytes: &[u8], layout: &Layout) -> Result<Self, ReplayError> {
+        let steamid = layout.read(bytes, Field::SteamId)?;
+
+        let timestamp = layout.read(bytes, Field::Timestamp)? as i64;
         let local: DateTime<Local> = Local::now();
-        let played_at = DateTime::from_timestamp(i64::from_le_bytes(*timestamp_bytes), 0)
-            .unwrap()
+        let played_at = DateTime::from_timestamp(timestamp, 0)
+            .ok_or(ReplayError::Parse(Field::Timestamp))?
             .with_timezone(&local.timezone());
 
-        let alt_byte = bytes[0x20];
+        let alt_byte = layout.read_u8(bytes, Field::Alt)?;
         let is_versus = alt_byte == 0x03;
 
-        let mode_byte = bytes[0x24];
-        let shiranui_tier_byte = bytes[0x48];
-        let shiranui_points_byte = bytes[0x0C];
+        let mode_byte = layout.read_u8(bytes, Field::Mode)?;
+        let shiranui_tier_byte = layout.read_u8(bytes, Field::ShiranuiTier)?;
+        let shiranui_points_byte = layout.read_u8(bytes, Field::ShiranuiPoints)?;
 
-        let rule_player_byte = bytes[0x28];
+        let rule_player_byte = layout.read_u8(bytes, Field::Rule)?;
         let rule_player = Rule::from(rule_player_byte);
 
         let mode = parse_mode(
@@ -182,28 +284,19 @@ impl Replay {
             shiranui_tier_byte,
             &rule_player,
         )
-        .ok_or(ReplayError::Parse)?;
+        .ok_or(ReplayError::Parse(Field::Mode))?;
 
-        let modifier_byte = bytes[0x30];
-        let modifiers = parse_modifier(modifier_byte);
+        let modifier_byte = layout.read_u8(bytes, Field::Modifiers)?;
+        let modifiers = parse_modifier(modifier_byte, layout);
 
-        let p1_seed = &bytes[0x34..0x38].try_into().map_err(ReplayError::Slice)?;
+        let time = GameTime(layout.read_u32(bytes, Field::Frames)?);
 
-        let frame_time_bytes = &bytes[0x38..0x3C].try_into().map_err(ReplayError::Slice)?;
-        let time = Duration::from_millis(
-            (((100.0 / 6.0) * (u32::from_le_bytes(*frame_time_bytes) as f32)) as u64),
-        );
-        let level_bytes = &bytes[0x3C..0x40].try_into().map_err(ReplayError::Slice)?;
-        let score_bytes = &bytes[0x40..0x44].try_into().map_err(ReplayError::Slice)?;
-
-        let bravo = bytes[0x44];
+        let bravo = layout.read_u8(bytes, Field::Bravo)?;
 
         let opponent = if is_versus {
-            let seed_bytes = &bytes[0x104..0x108].try_into().map_err(ReplayError::Slice)?;
-            let rule_aux_byte = bytes[0x2C];
             Some(Opponent {
-                seed: u32::from_le_bytes(*seed_bytes),
-                rule: Rule::from(rule_aux_byte),
+                seed: layout.read_u32(bytes, Field::OpponentSeed)?,
+                rule: Rule::from(layout.read_u8(bytes, Field::OpponentRule)?),
             })
         } else {
             None
@@ -212,17 +305,39 @@ impl Replay {
         Ok(Replay {
             mode,
             rule: rule_player,
-            steamid: u64::from_le_bytes(*steam_id_bytes),
+            steamid: SteamId::from(steamid),
             played_at,
             modifiers,
-            score: u32::from_le_bytes(*score_bytes),
+            modifier_byte,
+            score: layout.read_u32(bytes, Field::Score)?,
             time,
-            level: u32::from_le_bytes(*level_bytes),
+            level: layout.read_u32(bytes, Field::Level)?,
             bravo,
-            seed: u32::from_le_bytes(*p1_seed),
+            seed: layout.read_u32(bytes, Field::Seed)?,
             opponent,
+            integrity: layout.integrity(bytes),
         })
     }
+
+    pub fn unknown_modifiers(&self) -> Option<Mod> {
+        self.modifiers
+            .iter()
+            .find(|m| matches!(m, Mod::Unknown(_)))
+            .copied()
+    }
+
+    /// Only once the size and checksum were checked, an unchecked file may be anything.
+    pub fn is_intact(&self) -> bool {
+        self.integrity == Integrity::Intact
+    }
+
+    /// The size or checksum didn't match, a corrupted or hand-edited file.
+    pub fn is_corrupted(&self) -> bool {
+        matches!(
+            self.integrity,
+            Integrity::SizeMismatch { .. } | Integrity::ChecksumMismatch { .. }
+        )
+    }
 }
 
 #[derive(Default, Debug, Deserialize)]
@@ -234,60 +349,131 @@ pub struct ReplayStore {
     pub shiranui: Vec<Replay>,
     pub konoha: Vec<Replay>,
     pub pvp: Vec<Replay>,
+    /// Replays written by a build none of the layouts match.
+    pub unsupported: Vec<PathBuf>,
+    #[serde(skip)]
+    pub diagnostics: Diagnostics,
 }
 
 impl ReplayStore {
     pub fn new() -> Self {
         let mut store: ReplayStore = Default::default();
+
+        //TODO rewrite with maps?
+        for path in Self::replay_paths(&mut store.diagnostics) {
+            let bytes = match std::fs::read(&path) {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    store
+                        .diagnostics
+                        .error(DiagnosticKind::Io, Some(path), e.to_string());
+                    continue;
+                }
+            };
+            match Replay::from_bytes(&bytes) {
+                Ok(r) => {
+                    if let Some(Mod::Unknown(bits)) = r.unknown_modifiers() {
+                        store.diagnostics.warn(
+                            DiagnosticKind::UnknownModifier,
+                            Some(path.clone()),
+                            format!(
+                                "Unknown modifier bits {bits:#010b} (byte {:#010b})",
+                                r.modifier_byte
+                            ),
+                        );
+                    }
+                    if r.is_corrupted() {
+                        store.diagnostics.warn(
+                            DiagnosticKind::Corrupted,
+                            Some(path.clone()),
+                            r.integrity.to_string(),
+                        );
+                    }
+                    if r.opponent.is_some() && !matches!(r.mode, Mode::Shiranui(_, _)) {
+                        store.pvp.push(r);
+                        continue;
+                    }
+                    match r.mode {
+                        Mode::Marathon => store.marathon.push(r),
+                        Mode::Master => store.master.push(r),
+                        Mode::Normal => store.normal.push(r),
+                        Mode::Konoha(_) => store.konoha.push(r),
+                        Mode::Shiranui(_, _) => store.shiranui.push(r),
+                        Mode::Asuka => store.asuka.push(r),
+                        Mode::Versus => store.diagnostics.error(
+                            DiagnosticKind::Parse,
+                            Some(path),
+                            String::from("Versus replay without an opponent"),
+                        ),
+                    }
+                }
+                Err(e @ ReplayError::UnsupportedVersion) => {
+                    store.diagnostics.warn(
+                        DiagnosticKind::UnsupportedVersion,
+                        Some(path.clone()),
+                        e.to_string(),
+                    );
+                    store.unsupported.push(path);
+                }
+                Err(e) => {
+                    store
+                        .diagnostics
+                        .error(DiagnosticKind::Parse, Some(path), e.to_string());
+                }
+            };
+        }
+        store
+    }
+
+    pub fn root_folder() -> PathBuf {
         #[cfg(unix)]
         //TODO ask for path somewhere else later etc, pass as argument instead
         let root_folder = PathBuf::from(
             "/Nagi/SteamLibrary/steamapps/compatdata/3328480/pfx/drive_c/users/steamuser/AppData/Local/tgm4/savedata/",
         );
         #[cfg(windows)]
-        let root_folder = std::env::var("APPDATA").expect("No APPDATA directory");
+        let root_folder = PathBuf::from(std::env::var("APPDATA").expect("No APPDATA directory"));
+        root_folder
+    }
 
-        //TODO rewrite with maps?
+    /// Every replay `.bin` under the savedata folder.
+    pub fn replay_paths(diagnostics: &mut Diagnostics) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
         for entry in glob(&format!(
             "{}/**/replay_data/**/*.bin",
-            root_folder.display()
+            Self::root_folder().display()
         ))
         .expect("Failed to read glob pattern")
         {
             match entry {
-                Ok(path) => {
-                    let bytes = std::fs::read(&path).unwrap();
-                    match Replay::from_bytes(&bytes) {
-                        Ok(r) => {
-                            if r.opponent.is_some() && !matches!(r.mode, Mode::Shiranui(_, _)) {
-                                store.pvp.push(r);
-                                continue;
-                            }
-                            match r.mode {
-                                Mode::Marathon => store.marathon.push(r),
-                                Mode::Master => store.master.push(r),
-                                Mode::Normal => store.normal.push(r),
-                                Mode::Konoha(_) => store.konoha.push(r),
-                                Mode::Shiranui(_, _) => store.shiranui.push(r),
-                                Mode::Asuka => store.asuka.push(r),
-                                Mode::Versus => {
-                                    eprintln!("{} is incorrect versus.", path.display())
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            eprintln!("Error on path {}: {e}", path.display())
-                        }
-                    };
-                }
-                Err(e) => eprintln!("{:?}", e),
+                Ok(path) => paths.push(path),
+                Err(e) => diagnostics.error(
+                    DiagnosticKind::Io,
+                    Some(e.path().to_path_buf()),
+                    e.error().to_string(),
+                ),
             }
         }
-        store
+        paths
+    }
+
+    /// Every replay, mode by mode.
+    pub fn all(&self) -> impl Iterator<Item = &Replay> {
+        [
+            &self.normal,
+            &self.marathon,
+            &self.asuka,
+            &self.master,
+            &self.shiranui,
+            &self.konoha,
+            &self.pvp,
+        ]
+        .into_iter()
+        .flatten()
     }
 
-    pub fn get_steam_ids(&self) -> Vec<u64> {
-        let mut ids: HashSet<u64> = HashSet::new();
+    pub fn get_steam_ids(&self) -> Vec<SteamId> {
+        let mut ids: HashSet<SteamId> = HashSet::new();
         self.normal.iter().for_each(|r| {
             //
             ids.insert(r.steamid);
@@ -316,7 +502,7 @@ impl ReplayStore {
             //
             ids.insert(r.steamid);
         });
-        ids.into_iter().collect::<Vec<u64>>()
+        ids.into_iter().collect::<Vec<SteamId>>()
     }
 }
 
@@ -326,18 +512,112 @@ mod tests {
 
     #[test]
     fn test_bitflags() {
-        assert_eq!(parse_modifier(0b11111111), vec![
-            Mod::Daily,
-            Mod::Easy,
-            Mod::Big,
-            Mod::MaxG
-        ]);
-        assert_eq!(parse_modifier(0b10000000), vec![]);
-        assert_eq!(parse_modifier(0b01000000), vec![Mod::Daily]);
-        assert_eq!(parse_modifier(0b00110000), vec![Mod::Easy]);
-        assert_eq!(parse_modifier(0b00110010), vec![Mod::Easy, Mod::Big]);
-        assert_eq!(parse_modifier(0b00001100), vec![]);
-        assert_eq!(parse_modifier(0b00000010), vec![Mod::Big]);
-        assert_eq!(parse_modifier(0b00000001), vec![Mod::MaxG]);
+        assert_eq!(
+            parse_modifier(0b11111111, layout::CURRENT),
+            vec![
+                Mod::Daily,
+                Mod::Easy,
+                Mod::Big,
+                Mod::MaxG,
+                Mod::Unknown(0b10001100)
+            ]
+        );
+        assert_eq!(
+            parse_modifier(0b10000000, layout::CURRENT),
+            vec![Mod::Unknown(0b10000000)]
+        );
+        assert_eq!(
+            parse_modifier(0b01000000, layout::CURRENT),
+            vec![Mod::Daily]
+        );
+        assert_eq!(parse_modifier(0b00110000, layout::CURRENT), vec![Mod::Easy]);
+        assert_eq!(
+            parse_modifier(0b00110010, layout::CURRENT),
+            vec![Mod::Easy, Mod::Big]
+        );
+        assert_eq!(
+            parse_modifier(0b00001100, layout::CURRENT),
+            vec![Mod::Unknown(0b00001100)]
+        );
+        assert_eq!(
+            parse_modifier(0b00010001, layout::CURRENT),
+            vec![Mod::MaxG, Mod::Unknown(0b00010000)]
+        );
+        assert_eq!(parse_modifier(0b00000010, layout::CURRENT), vec![Mod::Big]);
+        assert_eq!(parse_modifier(0b00000001, layout::CURRENT), vec![Mod::MaxG]);
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let mut bytes = vec![0u8; layout::V1.header_len()];
+        assert!(Replay::from_bytes(&bytes).is_ok());
+        bytes[0x24] = 0x02;
+        assert!(matches!(
+            Replay::from_bytes(&bytes),
+            Err(ReplayError::UnsupportedVersion)
+        ));
+        // long enough for the signature, so not merely truncated
+        assert!(matches!(
+            Replay::from_bytes(&bytes[..0x30]),
+            Err(ReplayError::UnsupportedVersion)
+        ));
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let mut bytes = vec![0u8; layout::V1.header_len()];
+        bytes[0x18..0x20].copy_from_slice(&i64::MAX.to_le_bytes());
+        let error = Replay::from_bytes(&bytes).unwrap_err();
+        assert!(matches!(error, ReplayError::Parse(Field::Timestamp)));
+        assert_eq!(error.to_string(), "Invalid timestamp");
+    }
+
+    #[test]
+    fn test_truncated() {
+        let bytes = vec![0u8; layout::V1.header_len()];
+        assert!(matches!(
+            Replay::from_bytes(&bytes[..0x20]),
+            Err(ReplayError::Truncated(Field::Mode))
+        ));
+        assert!(matches!(
+            Replay::from_bytes(&bytes[..0x40]),
+            Err(ReplayError::Truncated(Field::ShiranuiTier))
+        ));
+        // the opponent seed is only read for versus
+        assert!(Replay::from_bytes(&bytes[..0x4C]).is_ok());
+        let mut versus = bytes[..0x4C].to_vec();
+        versus[0x20] = 0x03;
+        versus[0x24] = 0x04;
+        assert!(matches!(
+            Replay::from_bytes(&versus),
+            Err(ReplayError::Truncated(Field::OpponentSeed))
+        ));
+    }
+
+    #[test]
+    fn test_integrity() {
+        let mut replay = crate::stats::tests::replay(Mode::Master, 1, 999, 0, 600);
+        assert!(!replay.is_intact() && !replay.is_corrupted());
+        replay.integrity = Integrity::Intact;
+        assert!(replay.is_intact() && !replay.is_corrupted());
+        replay.integrity = Integrity::ChecksumMismatch {
+            expected: 1,
+            actual: 2,
+        };
+        assert!(!replay.is_intact() && replay.is_corrupted());
+    }
+
+    #[test]
+    fn test_game_time() {
+        assert_eq!(GameTime(0).to_string(), "00'00\"00");
+        assert_eq!(GameTime(1).as_centis(), 1);
+        assert_eq!(GameTime(3).as_centis(), 5);
+        assert_eq!(GameTime(60).as_centis(), 100);
+        assert_eq!(GameTime(59).to_string(), "00'00\"98");
+        assert_eq!(GameTime(60 * 61 + 30).to_string(), "01'01\"50");
+        // f32 only has 24 bits of mantissa, this used to come out a few centiseconds off
+        let marathon = GameTime(60 * 60 * 60 * 80 + 59);
+        assert_eq!(marathon.as_centis(), 28_800_098);
+        assert_eq!(marathon.to_string(), "4800'00\"98");
     }
 }
@@ -0,0 +1,646 @@
+//! A headless, deterministic sandbox for piece physics: board, rotation, gravity, lock delay
+//! and line clears, one frame per [`Simulator::step`].
+//!
+//! Nothing here has been checked against TGM4, so it can't validate replays. Their inputs and
+//! pieces aren't decoded, games are fed a piece sequence and inputs by hand.
+//!
+//! TGM rule pieces use ARS with its basic kicks and the center column rule, sonic drop on up
+//! and lock on down. Standard rule pieces use SRS kicks and hard drop. TGM4's timings per mode
+//! and level aren't known, [`Timing::default`] is TGM3 Master's first section. Levels count
+//! TGM style: one per piece, stopping at x99, and one per line cleared.
+//TODO decode the input stream and the score formula, then compare games with replay headers
+
+use std::{fmt, vec};
+
+use crate::replay::Rule;
+
+pub const WIDTH: usize = 10;
+/// 20 visible rows and 2 above them to spawn in.
+pub const HEIGHT: usize = 22;
+
+/// Where a piece's 4x4 box spawns, its top row and left column.
+const SPAWN: (i32, i32) = (3, 20);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Piece {
+    I,
+    Z,
+    S,
+    J,
+    L,
+    O,
+    T,
+}
+
+impl Piece {
+    /// In the games' internal order.
+    pub const ALL: [Piece; 7] = [
+        Piece::I,
+        Piece::Z,
+        Piece::S,
+        Piece::J,
+        Piece::L,
+        Piece::O,
+        Piece::T,
+    ];
+}
+
+impl fmt::Display for Piece {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+/// Buttons held on one frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Input {
+    pub left: bool,
+    pub right: bool,
+    pub up: bool,
+    pub down: bool,
+    pub cw: bool,
+    pub ccw: bool,
+}
+
+/// Frame counts, gravity is in 1/256 rows per frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timing {
+    pub gravity: u32,
+    pub are: u32,
+    pub line_are: u32,
+    pub line_clear: u32,
+    pub das: u32,
+    pub lock_delay: u32,
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Self {
+            gravity: 4,
+            are: 27,
+            line_are: 27,
+            line_clear: 40,
+            das: 16,
+            lock_delay: 30,
+        }
+    }
+}
+
+/// Cells of a rotation state, `(column, row)` in a 4x4 box with rows counted downwards.
+type Shape = [(i32, i32); 4];
+
+/// By piece in [`Piece::ALL`] order, then rotation state.
+const ARS: [[Shape; 4]; 7] = [
+    [
+        [(0, 1), (1, 1), (2, 1), (3, 1)],
+        [(2, 0), (2, 1), (2, 2), (2, 3)],
+        [(0, 1), (1, 1), (2, 1), (3, 1)],
+        [(2, 0), (2, 1), (2, 2), (2, 3)],
+    ],
+    [
+        [(0, 1), (1, 1), (1, 2), (2, 2)],
+        [(2, 0), (1, 1), (2, 1), (1, 2)],
+        [(0, 1), (1, 1), (1, 2), (2, 2)],
+        [(2, 0), (1, 1), (2, 1), (1, 2)],
+    ],
+    [
+        [(1, 1), (2, 1), (0, 2), (1, 2)],
+        [(0, 0), (0, 1), (1, 1), (1, 2)],
+        [(1, 1), (2, 1), (0, 2), (1, 2)],
+        [(0, 0), (0, 1), (1, 1), (1, 2)],
+    ],
+    [
+        [(0, 1), (1, 1), (2, 1), (2, 2)],
+        [(1, 0), (1, 1), (0, 2), (1, 2)],
+        [(0, 1), (0, 2), (1, 2), (2, 2)],
+        [(1, 0), (2, 0), (1, 1), (1, 2)],
+    ],
+    [
+        [(0, 1), (1, 1), (2, 1), (0, 2)],
+        [(0, 0), (1, 0), (1, 1), (1, 2)],
+        [(2, 1), (0, 2), (1, 2), (2, 2)],
+        [(1, 0), (1, 1), (1, 2), (2, 2)],
+    ],
+    [[(1, 1), (2, 1), (1, 2), (2, 2)]; 4],
+    [
+        [(0, 1), (1, 1), (2, 1), (1, 2)],
+        [(1, 0), (0, 1), (1, 1), (1, 2)],
+        [(1, 1), (0, 2), (1, 2), (2, 2)],
+        [(1, 0), (1, 1), (2, 1), (1, 2)],
+    ],
+];
+
+const SRS: [[Shape; 4]; 7] = [
+    [
+        [(0, 1), (1, 1), (2, 1), (3, 1)],
+        [(2, 0), (2, 1), (2, 2), (2, 3)],
+        [(0, 2), (1, 2), (2, 2), (3, 2)],
+        [(1, 0), (1, 1), (1, 2), (1, 3)],
+    ],
+    [
+        [(0, 0), (1, 0), (1, 1), (2, 1)],
+        [(2, 0), (1, 1), (2, 1), (1, 2)],
+        [(0, 1), (1, 1), (1, 2), (2, 2)],
+        [(1, 0), (0, 1), (1, 1), (0, 2)],
+    ],
+    [
+        [(1, 0), (2, 0), (0, 1), (1, 1)],
+        [(1, 0), (1, 1), (2, 1), (2, 2)],
+        [(1, 1), (2, 1), (0, 2), (1, 2)],
+        [(0, 0), (0, 1), (1, 1), (1, 2)],
+    ],
+    [
+        [(0, 0), (0, 1), (1, 1), (2, 1)],
+        [(1, 0), (2, 0), (1, 1), (1, 2)],
+        [(0, 1), (1, 1), (2, 1), (2, 2)],
+        [(1, 0), (1, 1), (0, 2), (1, 2)],
+    ],
+    [
+        [(2, 0), (0, 1), (1, 1), (2, 1)],
+        [(1, 0), (1, 1), (1, 2), (2, 2)],
+        [(0, 1), (1, 1), (2, 1), (0, 2)],
+        [(0, 0), (1, 0), (1, 1), (1, 2)],
+    ],
+    [[(1, 0), (2, 0), (1, 1), (2, 1)]; 4],
+    [
+        [(1, 0), (0, 1), (1, 1), (2, 1)],
+        [(1, 0), (1, 1), (2, 1), (1, 2)],
+        [(0, 1), (1, 1), (2, 1), (1, 2)],
+        [(1, 0), (0, 1), (1, 1), (1, 2)],
+    ],
+];
+
+fn shape(rule: Rule, piece: Piece, rotation: u8) -> Shape {
+    let index = Piece::ALL.iter().position(|p| *p == piece).unwrap_or(0);
+    match rule {
+        Rule::Tgm => ARS[index][rotation as usize],
+        Rule::Standard => SRS[index][rotation as usize],
+    }
+}
+
+/// SRS offsets to try, x right and y up.
+fn srs_kicks(piece: Piece, from: u8, to: u8) -> [(i32, i32); 5] {
+    match (piece == Piece::I, from, to) {
+        (false, 0, 1) | (false, 2, 1) => [(0, 0), (-1, 0), (-1, 1), (0, -2), (-1, -2)],
+        (false, 1, 0) | (false, 1, 2) => [(0, 0), (1, 0), (1, -1), (0, 2), (1, 2)],
+        (false, 2, 3) | (false, 0, 3) => [(0, 0), (1, 0), (1, 1), (0, -2), (1, -2)],
+        (false, 3, 2) | (false, 3, 0) => [(0, 0), (-1, 0), (-1, -1), (0, 2), (-1, 2)],
+        (true, 0, 1) | (true, 3, 2) => [(0, 0), (-2, 0), (1, 0), (-2, -1), (1, 2)],
+        (true, 1, 0) | (true, 2, 3) => [(0, 0), (2, 0), (-1, 0), (2, 1), (-1, -2)],
+        (true, 1, 2) | (true, 0, 3) => [(0, 0), (-1, 0), (2, 0), (-1, 2), (2, -1)],
+        (true, 2, 1) | (true, 3, 0) => [(0, 0), (1, 0), (-2, 0), (1, -2), (-2, 1)],
+        _ => [(0, 0); 5],
+    }
+}
+
+/// Row 0 is the bottom one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Board {
+    rows: Vec<[Option<Piece>; WIDTH]>,
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self {
+            rows: vec![[None; WIDTH]; HEIGHT],
+        }
+    }
+}
+
+impl Board {
+    pub fn get(&self, x: usize, y: usize) -> Option<Piece> {
+        self.rows
+            .get(y)
+            .and_then(|row| row.get(x).copied().flatten())
+    }
+
+    /// Walls and floor are solid, above the board isn't.
+    fn occupied(&self, x: i32, y: i32) -> bool {
+        if x < 0 || x >= WIDTH as i32 || y < 0 {
+            return true;
+        }
+        self.get(x as usize, y as usize).is_some()
+    }
+
+    pub fn filled(&self) -> usize {
+        self.rows.iter().flatten().filter(|c| c.is_some()).count()
+    }
+
+    fn clear_lines(&mut self) -> u32 {
+        let before = self.rows.len();
+        self.rows.retain(|row| row.iter().any(|c| c.is_none()));
+        let cleared = before - self.rows.len();
+        self.rows.resize(HEIGHT, [None; WIDTH]);
+        cleared as u32
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Active {
+    piece: Piece,
+    rotation: u8,
+    x: i32,
+    y: i32,
+    /// Gravity carried over between frames, in 1/256 rows.
+    gravity: u32,
+    lock: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Phase {
+    Are(u32),
+    Active(Active),
+    LineClear(u32),
+    Over,
+}
+
+#[derive(Debug, Clone)]
+pub struct Simulator {
+    rule: Rule,
+    timing: Timing,
+    /// Reaching it ends the game, like Master's 999.
+    max_level: Option<u32>,
+    /// Running out ends the game.
+    pieces_left: vec::IntoIter<Piece>,
+    board: Board,
+    phase: Phase,
+    previous: Input,
+    /// Frames left or right has been held, for DAS.
+    held: u32,
+    held_direction: i32,
+    level: u32,
+    lines: u32,
+    frame: u32,
+    pieces: u32,
+}
+
+impl Simulator {
+    /// Deals `pieces` in order, the first one spawns on the first step.
+    pub fn new(rule: Rule, pieces: impl IntoIterator<Item = Piece>) -> Self {
+        Self {
+            rule,
+            timing: Timing::default(),
+            max_level: None,
+            pieces_left: pieces.into_iter().collect::<Vec<_>>().into_iter(),
+            board: Board::default(),
+            phase: Phase::Are(0),
+            previous: Input::default(),
+            held: 0,
+            held_direction: 0,
+            level: 0,
+            lines: 0,
+            frame: 0,
+            pieces: 0,
+        }
+    }
+
+    pub fn with_timing(mut self, timing: Timing) -> Self {
+        self.timing = timing;
+        self
+    }
+
+    pub fn with_max_level(mut self, max_level: u32) -> Self {
+        self.max_level = Some(max_level);
+        self
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn level(&self) -> u32 {
+        self.level
+    }
+
+    pub fn lines(&self) -> u32 {
+        self.lines
+    }
+
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// Locked so far.
+    pub fn pieces(&self) -> u32 {
+        self.pieces
+    }
+
+    pub fn is_over(&self) -> bool {
+        matches!(self.phase, Phase::Over)
+    }
+
+    /// Steps through `inputs` until they run out or the game ends.
+    pub fn run(&mut self, inputs: impl IntoIterator<Item = Input>) {
+        for input in inputs {
+            if self.is_over() {
+                break;
+            }
+            self.step(input);
+        }
+    }
+
+    pub fn step(&mut self, input: Input) {
+        if self.is_over() {
+            return;
+        }
+        self.frame += 1;
+        let direction = match (input.left, input.right) {
+            (true, false) => -1,
+            (false, true) => 1,
+            _ => 0,
+        };
+        if direction != 0 && direction == self.held_direction {
+            self.held += 1;
+        } else {
+            self.held = 0;
+            self.held_direction = direction;
+        }
+
+        self.phase = match self.phase {
+            Phase::Are(0) => self.spawn(),
+            Phase::Are(frames) => Phase::Are(frames - 1),
+            Phase::LineClear(0) => Phase::Are(self.timing.line_are),
+            Phase::LineClear(frames) => Phase::LineClear(frames - 1),
+            Phase::Active(active) => self.control(active, input, direction),
+            Phase::Over => Phase::Over,
+        };
+        self.previous = input;
+    }
+
+    fn fits(&self, piece: Piece, rotation: u8, x: i32, y: i32) -> bool {
+        shape(self.rule, piece, rotation)
+            .iter()
+            .all(|(column, row)| !self.board.occupied(x + column, y - row))
+    }
+
+    fn spawn(&mut self) -> Phase {
+        let Some(piece) = self.pieces_left.next() else {
+            return Phase::Over;
+        };
+        let (x, y) = SPAWN;
+        if !self.fits(piece, 0, x, y) {
+            return Phase::Over;
+        }
+        if self.level % 100 != 99 && self.max_level.is_none_or(|max| self.level + 1 < max) {
+            self.level += 1;
+        }
+        Phase::Active(Active {
+            piece,
+            rotation: 0,
+            x,
+            y,
+            gravity: 0,
+            lock: 0,
+        })
+    }
+
+    fn control(&mut self, mut active: Active, input: Input, direction: i32) -> Phase {
+        if input.cw && !self.previous.cw {
+            self.rotate(&mut active, 1);
+        } else if input.ccw && !self.previous.ccw {
+            self.rotate(&mut active, 3);
+        }
+        if direction != 0
+            && (self.held == 0 || self.held >= self.timing.das)
+            && self.fits(
+                active.piece,
+                active.rotation,
+                active.x + direction,
+                active.y,
+            )
+        {
+            active.x += direction;
+        }
+
+        let hard_drop = input.up && !self.previous.up && self.rule == Rule::Standard;
+        if input.up && (self.rule == Rule::Tgm || hard_drop) {
+            while self.fits(active.piece, active.rotation, active.x, active.y - 1) {
+                active.y -= 1;
+                active.lock = 0;
+            }
+        }
+        if hard_drop {
+            return self.lock(active);
+        }
+
+        let gravity = if input.down {
+            self.timing.gravity.max(256)
+        } else {
+            self.timing.gravity
+        };
+        active.gravity += gravity;
+        while active.gravity >= 256 {
+            active.gravity -= 256;
+            if self.fits(active.piece, active.rotation, active.x, active.y - 1) {
+                active.y -= 1;
+                active.lock = 0;
+            } else {
+                active.gravity = 0;
+            }
+        }
+
+        if self.fits(active.piece, active.rotation, active.x, active.y - 1) {
+            return Phase::Active(active);
+        }
+        active.lock += 1;
+        if (input.down && self.rule == Rule::Tgm) || active.lock >= self.timing.lock_delay {
+            self.lock(active)
+        } else {
+            Phase::Active(active)
+        }
+    }
+
+    fn rotate(&self, active: &mut Active, turn: u8) {
+        let (piece, from) = (active.piece, active.rotation);
+        let to = (from + turn) % 4;
+        let kicks: Vec<(i32, i32)> = match self.rule {
+            Rule::Standard => srs_kicks(piece, from, to).to_vec(),
+            Rule::Tgm if piece == Piece::I => vec![(0, 0)],
+            Rule::Tgm
+                if matches!(piece, Piece::L | Piece::J | Piece::T)
+                    && self.center_blocked(active.x, active.y) =>
+            {
+                vec![(0, 0)]
+            }
+            Rule::Tgm => vec![(0, 0), (1, 0), (-1, 0)],
+        };
+        if let Some((dx, dy)) = kicks
+            .into_iter()
+            .find(|(dx, dy)| self.fits(piece, to, active.x + dx, active.y + dy))
+        {
+            active.rotation = to;
+            active.x += dx;
+            active.y += dy;
+        }
+    }
+
+    /// ARS doesn't kick L, J and T when the first occupied cell of their 3x3 box, in reading
+    /// order, is in the middle column. Cells the rotated piece wouldn't cover count too.
+    fn center_blocked(&self, x: i32, y: i32) -> bool {
+        (0..3)
+            .flat_map(|row| (0..3).map(move |column| (column, row)))
+            .find(|(column, row)| self.board.occupied(x + column, y - row))
+            .is_some_and(|(column, _)| column == 1)
+    }
+
+    fn lock(&mut self, active: Active) -> Phase {
+        for (column, row) in shape(self.rule, active.piece, active.rotation) {
+            let (x, y) = (active.x + column, active.y - row);
+            if let Some(cell) = self
+                .board
+                .rows
+                .get_mut(y as usize)
+                .and_then(|r| r.get_mut(x as usize))
+            {
+                *cell = Some(active.piece);
+            }
+        }
+        self.pieces += 1;
+        let lines = self.board.clear_lines();
+        if lines == 0 {
+            return Phase::Are(self.timing.are);
+        }
+        self.lines += lines;
+        self.level += lines;
+        if let Some(max) = self.max_level
+            && self.level >= max
+        {
+            self.level = max;
+            return Phase::Over;
+        }
+        Phase::LineClear(self.timing.line_clear)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Starts with a T, every piece but the I is two rows tall when spawned.
+    fn pieces() -> Vec<Piece> {
+        use Piece::*;
+        [T, J, L, S, Z, O, I].repeat(30)
+    }
+
+    fn idle(simulator: &mut Simulator, until: impl Fn(&Simulator) -> bool) {
+        for _ in 0..100_000 {
+            if until(simulator) || simulator.is_over() {
+                return;
+            }
+            simulator.step(Input::default());
+        }
+        panic!("never happened");
+    }
+
+    #[test]
+    fn test_gravity_and_lock() {
+        let timing = Timing {
+            gravity: 256,
+            ..Timing::default()
+        };
+        let mut simulator = Simulator::new(Rule::Tgm, pieces()).with_timing(timing);
+        idle(&mut simulator, |s| s.pieces() == 1);
+        assert_eq!(simulator.board().filled(), 4);
+        assert_eq!(simulator.level(), 1);
+        // spawned on frame 1, two rows tall so on the floor 18 frames later, and locked on the
+        // lock delay's last frame there
+        let bottom = (0..WIDTH).filter(|x| simulator.board().get(*x, 0).is_some());
+        assert!(bottom.count() > 0);
+        assert_eq!(simulator.frame(), 1 + 18 + timing.lock_delay - 1);
+
+        idle(&mut simulator, |s| s.is_over());
+        assert!(simulator.pieces() > 4);
+        assert_eq!(simulator.lines(), 0);
+    }
+
+    #[test]
+    fn test_line_clear() {
+        for rule in [Rule::Tgm, Rule::Standard] {
+            let mut simulator = Simulator::new(rule, pieces());
+            for x in (0..WIDTH).filter(|x| !(3..7).contains(x)) {
+                simulator.board.rows[0][x] = Some(Piece::O);
+            }
+            // make the next piece an I
+            simulator.phase = Phase::Active(Active {
+                piece: Piece::I,
+                rotation: 0,
+                x: 3,
+                y: 20,
+                gravity: 0,
+                lock: 0,
+            });
+            let drop = Input {
+                up: true,
+                ..Input::default()
+            };
+            simulator.step(drop);
+            simulator.step(Input {
+                down: true,
+                ..Input::default()
+            });
+            if rule == Rule::Tgm {
+                assert_eq!(simulator.pieces(), 1, "down locks a grounded piece");
+            }
+            assert_eq!(simulator.lines(), 1, "{rule}");
+            assert_eq!(simulator.board().filled(), 0);
+        }
+    }
+
+    #[test]
+    fn test_level_stop() {
+        let mut simulator = Simulator::new(Rule::Tgm, pieces()).with_max_level(999);
+        simulator.level = 98;
+        simulator.step(Input::default());
+        assert_eq!(simulator.level(), 99);
+        simulator.phase = Phase::Are(0);
+        simulator.step(Input::default());
+        assert_eq!(simulator.level(), 99);
+    }
+
+    #[test]
+    fn test_wall_kick() {
+        let mut simulator = Simulator::new(Rule::Tgm, pieces());
+        let mut active = Active {
+            piece: Piece::T,
+            rotation: 3,
+            x: -1,
+            y: 10,
+            gravity: 0,
+            lock: 0,
+        };
+        assert!(simulator.fits(Piece::T, 3, -1, 10));
+        simulator.rotate(&mut active, 1);
+        assert_eq!((active.rotation, active.x), (0, 0));
+
+        // L kicks right here, unless the first blocked cell is in the middle column
+        let mut active = Active {
+            piece: Piece::L,
+            rotation: 0,
+            x: 3,
+            y: 10,
+            ..active
+        };
+        simulator.board.rows[10][3] = Some(Piece::O);
+        simulator.rotate(&mut active, 1);
+        assert_eq!((active.rotation, active.x), (1, 4));
+
+        simulator.board.rows[10][3] = None;
+        simulator.board.rows[10][4] = Some(Piece::O);
+        let mut active = Active {
+            rotation: 0,
+            x: 3,
+            ..active
+        };
+        simulator.rotate(&mut active, 1);
+        assert_eq!((active.rotation, active.x), (0, 3));
+
+        // the whole box is scanned, the corner comes first though the J wouldn't cover it
+        simulator.board.rows[10][3] = Some(Piece::O);
+        let mut active = Active {
+            piece: Piece::J,
+            ..active
+        };
+        simulator.rotate(&mut active, 1);
+        assert_eq!((active.rotation, active.x), (1, 4));
+    }
+}
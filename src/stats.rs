@@ -0,0 +1,680 @@
+//! Comparing and aggregating results, shared by the player page and the dashboards.
+
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+use chrono::{DateTime, Local, TimeDelta};
+
+use crate::{
+    replay::{GameTime, Mode, Replay, Rule},
+    steam_id::SteamId,
+};
+
+/// What a mode's leaderboard is sorted by, ties go to the fastest time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ranking {
+    Level,
+    Score,
+    /// Shiranui's tier, then its points.
+    Tier,
+}
+
+impl Ranking {
+    pub fn of(mode: Mode) -> Self {
+        match mode {
+            Mode::Normal | Mode::Konoha(_) => Ranking::Score,
+            Mode::Shiranui(_, _) => Ranking::Tier,
+            Mode::Marathon | Mode::Master | Mode::Asuka | Mode::Versus => Ranking::Level,
+        }
+    }
+}
+
+/// `Greater` when `a` is the better result, both should be from the same mode.
+pub fn compare(a: &Replay, b: &Replay) -> Ordering {
+    let primary = match (Ranking::of(a.mode), a.mode, b.mode) {
+        (Ranking::Tier, Mode::Shiranui(tier_a, points_a), Mode::Shiranui(tier_b, points_b)) => {
+            (tier_a, points_a).cmp(&(tier_b, points_b))
+        }
+        (Ranking::Score, _, _) => a.score.cmp(&b.score),
+        _ => Ordering::Equal,
+    };
+    primary
+        .then(a.level.cmp(&b.level))
+        .then(b.time.cmp(&a.time))
+}
+
+pub fn best<'a>(replays: impl IntoIterator<Item = &'a Replay>) -> Option<&'a Replay> {
+    replays.into_iter().max_by(|a, b| compare(a, b))
+}
+
+/// One player's results in a mode and rule.
+#[derive(Debug)]
+pub struct ModeStats<'a> {
+    pub count: usize,
+    pub best_level: u32,
+    pub best_score: u32,
+    /// The best run as ranked by [`compare`], its time is the one worth showing.
+    pub best: &'a Replay,
+}
+
+/// Everything one player did, see [`PlayerStats::new`].
+#[derive(Debug)]
+pub struct PlayerStats<'a> {
+    pub count: usize,
+    pub first_played: Option<DateTime<Local>>,
+    pub last_played: Option<DateTime<Local>>,
+    /// Keyed by [`Mode::group`] name and rule, so the order is stable.
+    pub modes: BTreeMap<(String, Rule), ModeStats<'a>>,
+    pub versus: Vec<VersusMatch<'a>>,
+}
+
+impl<'a> PlayerStats<'a> {
+    pub fn new(id: SteamId, replays: impl IntoIterator<Item = &'a Replay>) -> Self {
+        let replays: Vec<&Replay> = replays.into_iter().collect();
+        let mut stats = PlayerStats {
+            count: 0,
+            first_played: None,
+            last_played: None,
+            modes: BTreeMap::new(),
+            versus: versus_matches(replays.iter().copied())
+                .into_iter()
+                .filter_map(|m| m.from_side_of(id))
+                .collect(),
+        };
+        for replay in replays.into_iter().filter(|r| r.steamid == id) {
+            stats.count += 1;
+            let played_at = replay.played_at;
+            stats.first_played = Some(stats.first_played.map_or(played_at, |d| d.min(played_at)));
+            stats.last_played = Some(stats.last_played.map_or(played_at, |d| d.max(played_at)));
+            stats
+                .modes
+                .entry((replay.mode.group().to_string(), replay.rule))
+                .and_modify(|m| {
+                    m.count += 1;
+                    m.best_level = m.best_level.max(replay.level);
+                    m.best_score = m.best_score.max(replay.score);
+                    if compare(replay, m.best).is_gt() {
+                        m.best = replay;
+                    }
+                })
+                .or_insert(ModeStats {
+                    count: 1,
+                    best_level: replay.level,
+                    best_score: replay.score,
+                    best: replay,
+                });
+        }
+        stats
+    }
+}
+
+/// The best result in a mode, rule and modifier set, with the one it beat.
+#[derive(Debug)]
+pub struct PersonalBest<'a> {
+    pub mode: Mode,
+    pub rule: Rule,
+    pub best: &'a Replay,
+    /// What was the best right before `best` was played.
+    pub previous: Option<&'a Replay>,
+}
+
+impl PersonalBest<'_> {
+    pub fn margin(&self) -> Option<Margin> {
+        self.previous
+            .map(|previous| Margin::between(self.best, previous))
+    }
+}
+
+/// How much a result beat another by, in what the mode is ranked by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Margin {
+    Levels(i64),
+    Points(i64),
+    Tiers(i64),
+    /// Same result, but faster.
+    Faster(GameTime),
+}
+
+impl Margin {
+    pub fn between(better: &Replay, worse: &Replay) -> Self {
+        let diff = |a: u32, b: u32| a as i64 - b as i64;
+        match (Ranking::of(better.mode), better.mode, worse.mode) {
+            (Ranking::Tier, Mode::Shiranui(a, _), Mode::Shiranui(b, _)) if a != b => {
+                Margin::Tiers(diff(a.into(), b.into()))
+            }
+            (Ranking::Tier, Mode::Shiranui(_, a), Mode::Shiranui(_, b)) if a != b => {
+                Margin::Points(diff(a.into(), b.into()))
+            }
+            (Ranking::Score, _, _) if better.score != worse.score => {
+                Margin::Points(diff(better.score, worse.score))
+            }
+            _ if better.level != worse.level => Margin::Levels(diff(better.level, worse.level)),
+            _ => Margin::Faster(GameTime(worse.time.0.saturating_sub(better.time.0))),
+        }
+    }
+}
+
+impl fmt::Display for Margin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Margin::Levels(levels) => write!(f, "{levels:+} levels"),
+            Margin::Points(points) => write!(f, "{points:+} points"),
+            Margin::Tiers(tiers) => write!(f, "{tiers:+} tiers"),
+            Margin::Faster(time) => write!(f, "-{time}"),
+        }
+    }
+}
+
+/// `id`'s bests per mode, rule and modifier set, oldest mode first.
+pub fn personal_bests<'a>(
+    id: SteamId,
+    replays: impl IntoIterator<Item = &'a Replay>,
+) -> Vec<PersonalBest<'a>> {
+    let mut groups: BTreeMap<(String, Rule, u8), Vec<&Replay>> = BTreeMap::new();
+    for replay in replays.into_iter().filter(|r| r.steamid == id) {
+        groups
+            .entry((
+                replay.mode.group().to_string(),
+                replay.rule,
+                replay.modifier_byte,
+            ))
+            .or_default()
+            .push(replay);
+    }
+    groups
+        .into_values()
+        .map(|mut replays| {
+            replays.sort_by_key(|r| r.played_at);
+            let mut best = replays[0];
+            let mut previous = None;
+            for replay in &replays[1..] {
+                if compare(replay, best).is_gt() {
+                    previous = Some(best);
+                    best = replay;
+                }
+            }
+            PersonalBest {
+                mode: best.mode.group(),
+                rule: best.rule,
+                best,
+                previous,
+            }
+        })
+        .collect()
+}
+
+/// How a session's best compares to an earlier session's best in the same mode and rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Progress {
+    Improved(Margin),
+    Matched,
+    /// By how much the earlier best was better.
+    Regressed(Margin),
+}
+
+impl Progress {
+    pub fn between(current: &Replay, previous: &Replay) -> Self {
+        match compare(current, previous) {
+            Ordering::Greater => Progress::Improved(Margin::between(current, previous)),
+            Ordering::Equal => Progress::Matched,
+            Ordering::Less => Progress::Regressed(Margin::between(previous, current)),
+        }
+    }
+}
+
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Progress::Improved(margin) => write!(f, "{margin}"),
+            Progress::Matched => write!(f, "="),
+            Progress::Regressed(Margin::Levels(levels)) => write!(f, "{:+} levels", -levels),
+            Progress::Regressed(Margin::Points(points)) => write!(f, "{:+} points", -points),
+            Progress::Regressed(Margin::Tiers(tiers)) => write!(f, "{:+} tiers", -tiers),
+            Progress::Regressed(Margin::Faster(time)) => write!(f, "+{time}"),
+        }
+    }
+}
+
+/// Replays played without a break longer than the gap given to [`sessions`].
+#[derive(Debug)]
+pub struct Session<'a> {
+    /// Oldest first, never empty.
+    pub replays: Vec<&'a Replay>,
+    /// Keyed like [`PlayerStats::modes`].
+    pub bests: BTreeMap<(String, Rule), &'a Replay>,
+    /// Against the best of the last session that played the same mode and rule.
+    pub progress: BTreeMap<(String, Rule), Progress>,
+}
+
+impl Session<'_> {
+    pub fn start(&self) -> DateTime<Local> {
+        self.replays[0].played_at
+    }
+
+    pub fn end(&self) -> DateTime<Local> {
+        self.replays[self.replays.len() - 1].played_at
+    }
+
+    /// Sum of the games' times, menus and loading screens aren't counted.
+    pub fn playtime(&self) -> GameTime {
+        GameTime(self.replays.iter().map(|r| r.time.0).sum())
+    }
+}
+
+/// `id`'s replays split wherever more than `gap` passed between two games, oldest first.
+pub fn sessions<'a>(
+    id: SteamId,
+    replays: impl IntoIterator<Item = &'a Replay>,
+    gap: TimeDelta,
+) -> Vec<Session<'a>> {
+    let mut replays: Vec<&Replay> = replays.into_iter().filter(|r| r.steamid == id).collect();
+    replays.sort_by_key(|r| r.played_at);
+
+    let mut groups: Vec<Vec<&Replay>> = Vec::new();
+    for replay in replays {
+        match groups.last_mut() {
+            Some(group) if replay.played_at - group[group.len() - 1].played_at <= gap => {
+                group.push(replay)
+            }
+            _ => groups.push(vec![replay]),
+        }
+    }
+
+    let mut last_bests: BTreeMap<(String, Rule), &Replay> = BTreeMap::new();
+    groups
+        .into_iter()
+        .map(|replays| {
+            let mut bests: BTreeMap<(String, Rule), &Replay> = BTreeMap::new();
+            for replay in &replays {
+                bests
+                    .entry((replay.mode.group().to_string(), replay.rule))
+                    .and_modify(|best| {
+                        if compare(replay, best).is_gt() {
+                            *best = replay;
+                        }
+                    })
+                    .or_insert(replay);
+            }
+            let progress = bests
+                .iter()
+                .filter_map(|(key, best)| {
+                    let previous = last_bests.get(key)?;
+                    Some((key.clone(), Progress::between(best, previous)))
+                })
+                .collect();
+            last_bests.extend(bests.iter().map(|(key, best)| (key.clone(), *best)));
+            Session {
+                replays,
+                bests,
+                progress,
+            }
+        })
+        .collect()
+}
+
+/// Every replay of `seed`, best first within each mode and rule.
+///
+/// The same seed deals the same pieces, keyed like [`PlayerStats::modes`].
+pub fn same_seed<'a>(
+    seed: u32,
+    replays: impl IntoIterator<Item = &'a Replay>,
+) -> BTreeMap<(String, Rule), Vec<&'a Replay>> {
+    let mut groups: BTreeMap<(String, Rule), Vec<&Replay>> = BTreeMap::new();
+    for replay in replays.into_iter().filter(|r| r.seed == seed) {
+        groups
+            .entry((replay.mode.group().to_string(), replay.rule))
+            .or_default()
+            .push(replay);
+    }
+    for replays in groups.values_mut() {
+        replays.sort_by(|a, b| compare(b, a));
+    }
+    groups
+}
+
+/// Both sides of a versus match, found by their swapped seeds.
+#[derive(Debug, Clone, Copy)]
+pub struct VersusMatch<'a> {
+    pub player: &'a Replay,
+    pub opponent: &'a Replay,
+}
+
+impl<'a> VersusMatch<'a> {
+    pub fn from_side_of(self, id: SteamId) -> Option<Self> {
+        if self.player.steamid == id {
+            Some(self)
+        } else if self.opponent.steamid == id {
+            Some(VersusMatch {
+                player: self.opponent,
+                opponent: self.player,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// A record over some versus matches. Without the winner decoded, how far each side got
+/// stands in for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct HeadToHead {
+    pub matches: usize,
+    /// Matches where the player reached a higher level.
+    pub ahead: usize,
+    /// Matches where the opponent reached a higher level.
+    pub behind: usize,
+    /// Sum of the player's level minus the opponent's.
+    pub level_difference: i64,
+}
+
+impl HeadToHead {
+    pub fn of(versus: &[VersusMatch<'_>]) -> Self {
+        let mut record = Self::default();
+        versus.iter().for_each(|m| record.add(m));
+        record
+    }
+
+    fn add(&mut self, m: &VersusMatch<'_>) {
+        let difference = m.player.level as i64 - m.opponent.level as i64;
+        self.matches += 1;
+        self.level_difference += difference;
+        match difference.cmp(&0) {
+            Ordering::Greater => self.ahead += 1,
+            Ordering::Less => self.behind += 1,
+            Ordering::Equal => {}
+        }
+    }
+
+    pub fn mean_level_difference(&self) -> f64 {
+        self.level_difference as f64 / self.matches.max(1) as f64
+    }
+}
+
+/// Matches seen from one player's side, split by opponent, most played first.
+pub fn head_to_head(versus: &[VersusMatch<'_>]) -> Vec<(SteamId, HeadToHead)> {
+    let mut opponents: BTreeMap<SteamId, HeadToHead> = BTreeMap::new();
+    for m in versus {
+        opponents.entry(m.opponent.steamid).or_default().add(m);
+    }
+    let mut records: Vec<(SteamId, HeadToHead)> = opponents.into_iter().collect();
+    records.sort_by_key(|(_, record)| Reverse(record.matches));
+    records
+}
+
+/// Pairs up versus replays of the same match, each side stores the other's seed.
+///
+/// The winner isn't decoded yet, only who played whom and how far each got.
+pub fn versus_matches<'a>(replays: impl IntoIterator<Item = &'a Replay>) -> Vec<VersusMatch<'a>> {
+    let versus: Vec<&Replay> = replays
+        .into_iter()
+        .filter(|r| r.opponent.is_some())
+        .collect();
+    // the player page rebuilds this every frame, so look opponents up instead of trying every pair
+    let mut by_seed: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (i, replay) in versus.iter().enumerate() {
+        by_seed.entry(replay.seed).or_default().push(i);
+    }
+    let mut matches = Vec::new();
+    for (i, a) in versus.iter().enumerate() {
+        let Some(opponent_a) = a.opponent else {
+            continue;
+        };
+        let candidates = by_seed.get(&opponent_a.seed).into_iter().flatten();
+        for b in candidates.filter(|j| **j > i).map(|j| versus[*j]) {
+            if a.steamid != b.steamid && b.opponent.is_some_and(|o| o.seed == a.seed) {
+                matches.push(VersusMatch {
+                    player: a,
+                    opponent: b,
+                });
+            }
+        }
+    }
+    matches
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use chrono::TimeDelta;
+
+    use crate::replay::{Integrity, Mod, Opponent};
+
+    pub fn replay(mode: Mode, steamid: u32, level: u32, score: u32, seconds: u32) -> Replay {
+        Replay {
+            mode,
+            rule: Rule::Tgm,
+            steamid: SteamId::from_account_id(steamid),
+            played_at: DateTime::from_timestamp(1_700_000_000 + seconds as i64, 0)
+                .unwrap()
+                .into(),
+            modifiers: Vec::new(),
+            modifier_byte: 0,
+            score,
+            seed: 0,
+            time: GameTime(seconds * 60),
+            level,
+            bravo: 0,
+            opponent: None,
+            integrity: Integrity::Unchecked,
+        }
+    }
+
+    #[test]
+    fn test_compare() {
+        let slow = replay(Mode::Master, 1, 999, 0, 600);
+        let fast = replay(Mode::Master, 1, 999, 0, 500);
+        let short = replay(Mode::Master, 1, 500, 0, 100);
+        assert!(compare(&fast, &slow).is_gt());
+        assert!(compare(&short, &slow).is_lt());
+        assert_eq!(best([&slow, &short, &fast]).unwrap().time, fast.time);
+
+        let high_score = replay(Mode::Normal, 1, 100, 9000, 600);
+        let high_level = replay(Mode::Normal, 1, 150, 5000, 600);
+        assert!(compare(&high_score, &high_level).is_gt());
+
+        let tier = replay(Mode::Shiranui(3, 0), 1, 100, 0, 600);
+        let points = replay(Mode::Shiranui(2, 90), 1, 300, 0, 600);
+        assert!(compare(&tier, &points).is_gt());
+    }
+
+    #[test]
+    fn test_player_stats() {
+        let replays = [
+            replay(Mode::Master, 1, 300, 10, 100),
+            replay(Mode::Master, 1, 999, 5, 900),
+            replay(Mode::Shiranui(1, 0), 1, 50, 0, 50),
+            replay(Mode::Shiranui(2, 0), 1, 40, 0, 60),
+            replay(Mode::Master, 2, 999, 99, 800),
+        ];
+        let stats = PlayerStats::new(SteamId::from_account_id(1), &replays);
+        assert_eq!(stats.count, 4);
+        assert_eq!(stats.first_played, Some(replays[2].played_at));
+        assert_eq!(stats.last_played, Some(replays[1].played_at));
+        let master = &stats.modes[&(String::from("Master"), Rule::Tgm)];
+        assert_eq!(
+            (master.count, master.best_level, master.best_score),
+            (2, 999, 10)
+        );
+        assert_eq!(master.best.time, GameTime(900 * 60));
+        let shiranui = &stats.modes[&(String::from("Shiranui"), Rule::Tgm)];
+        assert_eq!(shiranui.count, 2);
+        assert_eq!(shiranui.best.mode, Mode::Shiranui(2, 0));
+    }
+
+    #[test]
+    fn test_personal_bests() {
+        let mut big = replay(Mode::Master, 1, 999, 0, 400);
+        big.modifiers = vec![Mod::Big];
+        big.modifier_byte = 0b0010;
+        let mut replays = [
+            replay(Mode::Master, 1, 500, 0, 100),
+            replay(Mode::Master, 1, 400, 0, 200),
+            replay(Mode::Master, 1, 999, 0, 900),
+            replay(Mode::Master, 1, 999, 0, 700),
+            replay(Mode::Master, 1, 999, 0, 800),
+            replay(Mode::Normal, 1, 150, 2000, 300),
+            replay(Mode::Master, 2, 999, 0, 10),
+            big,
+        ];
+        // played in that order, a day apart
+        for (day, replay) in replays.iter_mut().enumerate() {
+            replay.played_at += TimeDelta::days(day as i64);
+        }
+        let bests = personal_bests(SteamId::from_account_id(1), &replays);
+        assert_eq!(bests.len(), 3);
+
+        let master = &bests[0];
+        assert_eq!(master.best.time, GameTime(700 * 60));
+        assert_eq!(master.previous.unwrap().time, GameTime(900 * 60));
+        assert_eq!(master.margin(), Some(Margin::Faster(GameTime(200 * 60))));
+        assert_eq!(master.margin().unwrap().to_string(), "-03'20\"00");
+
+        assert_eq!(bests[1].best.modifier_byte, 0b0010);
+        assert!(bests[1].margin().is_none());
+        assert_eq!(bests[2].mode, Mode::Normal);
+
+        assert_eq!(
+            Margin::between(&replays[2], &replays[0]).to_string(),
+            "+499 levels"
+        );
+    }
+
+    #[test]
+    fn test_versus_matches() {
+        let mut a = replay(Mode::Versus, 1, 120, 0, 90);
+        let mut b = replay(Mode::Versus, 2, 80, 0, 90);
+        let mut c = replay(Mode::Versus, 3, 80, 0, 90);
+        (a.seed, b.seed, c.seed) = (11, 22, 33);
+        a.opponent = Some(Opponent {
+            seed: 22,
+            rule: Rule::Standard,
+        });
+        b.opponent = Some(Opponent {
+            seed: 11,
+            rule: Rule::Tgm,
+        });
+        // played someone else with the same seed as a
+        c.opponent = Some(Opponent {
+            seed: 22,
+            rule: Rule::Tgm,
+        });
+        let matches = versus_matches([&a, &b, &c]);
+        assert_eq!(matches.len(), 1);
+        let from_b = matches[0].from_side_of(b.steamid).unwrap();
+        assert_eq!(from_b.player.steamid, b.steamid);
+        assert_eq!(from_b.opponent.level, 120);
+        assert!(matches[0].from_side_of(c.steamid).is_none());
+        let reversed = versus_matches([&c, &b, &a]);
+        assert_eq!(reversed.len(), 1);
+        assert_eq!(reversed[0].player.steamid, b.steamid);
+
+        let stats = PlayerStats::new(a.steamid, [&a, &b, &c]);
+        assert_eq!(stats.versus.len(), 1);
+        assert_eq!(stats.versus[0].opponent.steamid, b.steamid);
+    }
+
+    #[test]
+    fn test_head_to_head() {
+        let me = replay(Mode::Versus, 1, 0, 0, 0);
+        let levels = [(2, 300, 200), (3, 100, 250), (2, 400, 450), (2, 150, 150)];
+        let sides: Vec<(Replay, Replay)> = levels
+            .iter()
+            .map(|&(opponent, mine, theirs)| {
+                let mut player = me.clone();
+                player.level = mine;
+                (player, replay(Mode::Versus, opponent, theirs, 0, 0))
+            })
+            .collect();
+        let versus: Vec<VersusMatch<'_>> = sides
+            .iter()
+            .map(|(player, opponent)| VersusMatch { player, opponent })
+            .collect();
+
+        let records = head_to_head(&versus);
+        assert_eq!(records.len(), 2);
+        let (id, second) = records[0];
+        assert_eq!(id, SteamId::from_account_id(2));
+        assert_eq!(
+            second,
+            HeadToHead {
+                matches: 3,
+                ahead: 1,
+                behind: 1,
+                level_difference: 50,
+            }
+        );
+        assert_eq!(records[1].1.behind, 1);
+
+        let total = HeadToHead::of(&versus);
+        assert_eq!((total.matches, total.ahead, total.behind), (4, 1, 2));
+        assert!((total.mean_level_difference() - (-25.0)).abs() < 1e-9);
+        assert_eq!(HeadToHead::of(&[]).mean_level_difference(), 0.0);
+    }
+
+    #[test]
+    fn test_sessions() {
+        let mut replays = [
+            replay(Mode::Master, 1, 300, 0, 100),
+            replay(Mode::Master, 1, 500, 0, 200),
+            replay(Mode::Normal, 1, 100, 4000, 300),
+            replay(Mode::Master, 1, 400, 0, 150),
+            replay(Mode::Master, 1, 999, 0, 900),
+            replay(Mode::Master, 2, 999, 0, 10),
+        ];
+        // two games, the next day two games with a break between them, then one a week later
+        let start = replays[0].played_at;
+        for (replay, minutes) in replays.iter_mut().zip([0, 10, 1440, 1560, 11520, 1440]) {
+            replay.played_at = start + TimeDelta::minutes(minutes);
+        }
+        let sessions = sessions(SteamId::from_account_id(1), &replays, TimeDelta::hours(1));
+        assert_eq!(
+            sessions.iter().map(|s| s.replays.len()).collect::<Vec<_>>(),
+            [2, 1, 1, 1]
+        );
+        assert_eq!(sessions[0].playtime(), GameTime(300 * 60));
+        assert_eq!(sessions[0].start(), replays[0].played_at);
+        assert_eq!(sessions[0].end(), replays[1].played_at);
+
+        let master = (String::from("Master"), Rule::Tgm);
+        assert!(sessions[0].progress.is_empty());
+        // skipping a session that didn't play Master
+        assert_eq!(sessions[2].bests[&master].level, 400);
+        assert_eq!(
+            sessions[2].progress[&master],
+            Progress::Regressed(Margin::Levels(100))
+        );
+        assert_eq!(sessions[2].progress[&master].to_string(), "-100 levels");
+        assert_eq!(sessions[3].progress[&master].to_string(), "+599 levels");
+
+        let longer = super::sessions(SteamId::from_account_id(1), &replays, TimeDelta::days(2));
+        assert_eq!(longer.len(), 2);
+    }
+
+    #[test]
+    fn test_same_seed() {
+        let mut replays = [
+            replay(Mode::Master, 1, 300, 0, 100),
+            replay(Mode::Master, 2, 999, 0, 900),
+            replay(Mode::Master, 3, 999, 0, 800),
+            replay(Mode::Normal, 1, 100, 4000, 300),
+            replay(Mode::Master, 4, 999, 0, 10),
+        ];
+        for replay in &mut replays[..4] {
+            replay.seed = 1234;
+        }
+        let groups = same_seed(1234, &replays);
+        assert_eq!(groups.len(), 2);
+        let master = &groups[&(String::from("Master"), Rule::Tgm)];
+        assert_eq!(
+            master
+                .iter()
+                .map(|r| r.steamid.account_id())
+                .collect::<Vec<_>>(),
+            [3, 2, 1]
+        );
+        assert!(same_seed(1, &replays).is_empty());
+    }
+}
@@ -1,20 +1,373 @@
 use std::{
     collections::{HashMap, HashSet},
-    env,
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, Receiver, Sender},
+    },
+    thread::{self, sleep},
+    time::{Duration, Instant},
 };
 
-use serde::Deserialize;
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, de::DeserializeOwned};
+use ureq::{Agent, Body, http::Response as HttpResponse};
+
+use crate::{
+    diagnostics::{DiagnosticKind, Diagnostics},
+    name_cache::{AvatarUrls, CachedName, NameCache},
+    steam_id::SteamId,
+};
+
+pub const DEFAULT_BASE_URL: &str = "https://api.steampowered.com";
 
 #[derive(Default)]
 pub struct SteamApi {
-    ids: HashMap<u64, String>,
+    names: NameCache,
+    /// Cached names older than this are fetched again.
+    ttl: TimeDelta,
     api_key: String,
+    base_url: String,
+    /// Shared with the avatar downloads, so they count against the same limit.
+    throttle: Arc<Mutex<TokenBucket>>,
+    retry: RetryPolicy,
+    /// Where avatar images are cached, no avatars without one.
+    avatar_dir: Option<PathBuf>,
+    avatars: HashMap<(SteamId, AvatarSize), PathBuf>,
+    /// Names, avatars and friends coming in from the background fetches.
+    updates: Option<Receiver<Update>>,
+    /// Kept so every background fetch sends down the same channel.
+    sender: Option<Sender<Update>>,
+    pub diagnostics: Diagnostics,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AvatarSize {
+    /// 32x32
+    Small,
+    /// 64x64
+    Medium,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SteamError {
+    /// The API couldn't be reached at all.
+    Network(String),
+    /// An error status, after retrying if it was a transient one.
+    Status(u16),
+    /// The API refused the key (401/403).
+    BadKey,
+    /// The key is fine but the friend list isn't public, `GetFriendList` answers 401 for those.
+    Private,
+    Json(String),
+}
+
+impl fmt::Display for SteamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SteamError::Network(e) => write!(f, "Network error: {e}"),
+            SteamError::Status(status) => write!(f, "HTTP status {status}"),
+            SteamError::BadKey => write!(f, "The Steam Web API key was refused"),
+            SteamError::Private => write!(f, "Not public"),
+            SteamError::Json(e) => write!(f, "Unexpected response: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for SteamError {}
+
+impl SteamError {
+    fn kind(&self) -> DiagnosticKind {
+        match self {
+            SteamError::Json(_) => DiagnosticKind::Json,
+            _ => DiagnosticKind::Http,
+        }
+    }
+}
+
+/// Lets `capacity` requests through at once, then refills at `per_second`.
+#[derive(Debug)]
+pub struct TokenBucket {
+    capacity: f64,
+    per_second: f64,
+    /// Goes negative when requests are queued up waiting for a refill.
+    tokens: f64,
+    last: Instant,
+}
+
+impl Default for TokenBucket {
+    fn default() -> Self {
+        Self::new(5, 1.0)
+    }
+}
+
+impl TokenBucket {
+    pub fn new(capacity: u32, per_second: f64) -> Self {
+        Self {
+            capacity: capacity as f64,
+            per_second,
+            tokens: capacity as f64,
+            last: Instant::now(),
+        }
+    }
+
+    /// Takes a token, returning how long to wait before using it.
+    pub fn reserve(&mut self, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_second).min(self.capacity);
+        self.last = now;
+        self.tokens -= 1.0;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.per_second)
+        }
+    }
+}
+
+/// Exponential backoff for 429 and 5xx responses.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 4,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn delay(&self, attempt: u32) -> Duration {
+        self.base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay)
+    }
+}
+
+/// Everything a request needs to go through the throttle, cheap to move to another thread.
+struct Requester {
+    agent: Agent,
+    throttle: Arc<Mutex<TokenBucket>>,
+    retry: RetryPolicy,
+    api_key: String,
+    base_url: String,
+}
+
+impl Requester {
+    /// GETs `url` through the throttle, retrying transient errors, and returns the
+    /// response it ended on whatever its status.
+    fn get(&self, url: &str) -> Result<HttpResponse<Body>, SteamError> {
+        let mut attempt = 0;
+        loop {
+            let wait = self.throttle.lock().unwrap().reserve(Instant::now());
+            sleep(wait);
+            log::debug!("GET {}", self.redact(url));
+            let response = self
+                .agent
+                .get(url)
+                .call()
+                .map_err(|e| SteamError::Network(self.redact(&e.to_string())))?;
+            match response.status().as_u16() {
+                status @ (429 | 500..=599) if attempt < self.retry.max_retries => {
+                    let wait = retry_after(&response)
+                        .unwrap_or_else(|| self.retry.delay(attempt))
+                        .min(self.retry.max_delay);
+                    log::debug!("HTTP {status}, retrying in {wait:?}");
+                    sleep(wait);
+                    attempt += 1;
+                }
+                _ => return Ok(response),
+            }
+        }
+    }
+
+    fn get_json<T: DeserializeOwned>(&self, url: &str) -> Result<T, SteamError> {
+        read_json(self.get(url)?)
+    }
+
+    fn player_summaries(&self, chunk: &[SteamId]) -> Result<Vec<Player>, SteamError> {
+        let list = chunk
+            .iter()
+            .map(|e| e.to_string())
+            .collect::<Vec<String>>()
+            .join(",");
+        let url = format!(
+            "{}/ISteamUser/GetPlayerSummaries/v0002/?key={}&steamids={}",
+            self.base_url, &self.api_key, list
+        );
+        self.get_json::<SteamApiResponse>(&url)
+            .map(|r| r.response.players)
+    }
+
+    fn friend_list(&self, steam_id: SteamId) -> Result<Vec<SteamId>, SteamError> {
+        let url = format!(
+            "{}/ISteamUser/GetFriendList/v0001/?key={}&steamid={}&relationship=friend",
+            self.base_url, &self.api_key, steam_id
+        );
+        // only here does 401 mean the data is private rather than the key being bad
+        let friends = self
+            .get(&url)
+            .and_then(|response| match response.status().as_u16() {
+                401 => Err(SteamError::Private),
+                _ => read_json::<FriendListResponse>(response),
+            })?;
+        Ok(friends
+            .friendslist
+            .friends
+            .iter()
+            .filter_map(|f| f.steamid.parse::<u64>().ok().map(SteamId::from))
+            .collect())
+    }
+
+    fn download(&self, url: &str) -> Result<Vec<u8>, SteamError> {
+        let mut response = self.get(url)?;
+        match response.status().as_u16() {
+            200..=299 => response
+                .body_mut()
+                .read_to_vec()
+                .map_err(|e| SteamError::Network(e.to_string())),
+            status => Err(SteamError::Status(status)),
+        }
+    }
+
+    /// Hides the key from anything that ends up in logs or diagnostics.
+    fn redact(&self, text: &str) -> String {
+        if self.api_key.is_empty() {
+            text.to_string()
+        } else {
+            text.replace(&self.api_key, "<key>")
+        }
+    }
+}
+
+fn read_json<T: DeserializeOwned>(mut response: HttpResponse<Body>) -> Result<T, SteamError> {
+    match response.status().as_u16() {
+        200..=299 => response
+            .body_mut()
+            .read_json::<T>()
+            .map_err(|e| SteamError::Json(e.to_string())),
+        401 | 403 => Err(SteamError::BadKey),
+        status => Err(SteamError::Status(status)),
+    }
+}
+
+/// One avatar image to find on disk or download.
+#[derive(Debug)]
+struct AvatarJob {
+    id: SteamId,
+    size: AvatarSize,
+    url: String,
+    path: PathBuf,
+}
+
+impl AvatarJob {
+    fn run(self, requester: &Requester) -> AvatarUpdate {
+        let result = if self.path.exists() {
+            Ok(false)
+        } else {
+            requester
+                .download(&self.url)
+                .map_err(|e| (e.kind(), e.to_string()))
+                .and_then(|bytes| {
+                    self.path
+                        .parent()
+                        .map_or(Ok(()), fs::create_dir_all)
+                        .and_then(|_| fs::write(&self.path, bytes))
+                        .map_err(|e| (DiagnosticKind::Io, e.to_string()))
+                })
+                .map(|_| true)
+        };
+        AvatarUpdate { job: self, result }
+    }
+}
+
+/// A finished [`AvatarJob`], `Ok(true)` if it had to be downloaded.
+#[derive(Debug)]
+struct AvatarUpdate {
+    job: AvatarJob,
+    result: Result<bool, (DiagnosticKind, String)>,
+}
+
+/// One `GetPlayerSummaries` request.
+#[derive(Debug)]
+struct NamesUpdate {
+    chunk: Vec<SteamId>,
+    result: Result<Vec<Player>, SteamError>,
+}
+
+#[derive(Debug)]
+enum Update {
+    Names(NamesUpdate),
+    Avatar(AvatarUpdate),
+    Friends(SteamId, Result<Vec<SteamId>, SteamError>),
+}
+
+/// What [`SteamApi::poll`] took in.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Polled {
+    /// Names received, not counting the ids Steam didn't know.
+    pub names: usize,
+    /// Avatars downloaded rather than found on disk.
+    pub avatars: usize,
+    /// The list from [`SteamApi::fetch_friends_in_background`], once it worked.
+    pub friends: Option<Vec<SteamId>>,
+}
+
+/// Outcome of one `GetPlayerSummaries` request.
+#[derive(Debug)]
+pub struct ChunkOutcome {
+    pub ids: Vec<SteamId>,
+    pub error: Option<SteamError>,
+}
+
+#[derive(Debug, Default)]
+pub struct FetchSummary {
+    /// Ids skipped because their cached name is still fresh.
+    pub cached: usize,
+    /// Ids skipped because they can't belong to a player, see [`SteamId::is_player`].
+    pub spoofed: usize,
+    pub chunks: Vec<ChunkOutcome>,
+}
+
+impl FetchSummary {
+    pub fn succeeded(&self) -> impl Iterator<Item = &ChunkOutcome> {
+        self.chunks.iter().filter(|c| c.error.is_none())
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = &ChunkOutcome> {
+        self.chunks.iter().filter(|c| c.error.is_some())
+    }
 }
 
 #[derive(Deserialize, Debug)]
 pub struct Player {
     steamid: String,
     personaname: String,
+    #[serde(default)]
+    avatar: String,
+    #[serde(default)]
+    avatarmedium: String,
+}
+
+impl Player {
+    fn id(&self) -> Option<SteamId> {
+        self.steamid.parse::<u64>().ok().map(SteamId::from)
+    }
+
+    fn avatars(&self) -> Option<AvatarUrls> {
+        (!self.avatar.is_empty()).then(|| AvatarUrls {
+            small: self.avatar.clone(),
+            medium: self.avatarmedium.clone(),
+        })
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -27,60 +380,438 @@ pub struct SteamApiResponse {
     response: Response,
 }
 
+#[derive(Deserialize, Debug)]
+pub struct Friend {
+    steamid: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct FriendList {
+    friends: Vec<Friend>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct FriendListResponse {
+    friendslist: FriendList,
+}
+
 impl SteamApi {
     pub fn new(api_key: String) -> Self {
         Self {
             api_key,
+            base_url: String::from(DEFAULT_BASE_URL),
             ..Default::default()
         }
     }
-    pub fn add_ids(&mut self, id_list: &[u64]) {
-        for chunk in id_list.chunks(100) {
-            let list = chunk
-                .iter()
-                .map(|e| e.to_string())
-                .collect::<Vec<String>>()
-                .join(",");
-            let url = format!(
-                "http://api.steampowered.com/ISteamUser/GetPlayerSummaries/v0002/?key={}&steamids={}",
-                &self.api_key, list
+    pub fn with_cache(mut self, names: NameCache, ttl: TimeDelta) -> Self {
+        self.names = names;
+        self.ttl = ttl;
+        self
+    }
+
+    /// Points the client somewhere else than the official API, like a local stub.
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_throttle(mut self, throttle: TokenBucket) -> Self {
+        self.throttle = Arc::new(Mutex::new(throttle));
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn default_avatar_dir() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("tgm4-replay-manager").join("avatars"))
+    }
+
+    pub fn with_avatar_dir(mut self, avatar_dir: PathBuf) -> Self {
+        self.avatar_dir = Some(avatar_dir);
+        self
+    }
+
+    /// Fetches the names of ids that aren't cached or are older than the TTL.
+    ///
+    /// Failed chunks are reported in the summary and the diagnostics, a refused key
+    /// stops everything since every other request would fail the same way.
+    pub fn add_ids(&mut self, id_list: &[SteamId]) -> Result<FetchSummary, SteamError> {
+        let now = Utc::now();
+        let (stale, mut summary) = self.stale_ids(id_list, now);
+        let requester = self.requester();
+        for chunk in stale.chunks(100) {
+            let result = requester.player_summaries(chunk);
+            let bad_key = matches!(result, Err(SteamError::BadKey));
+            let outcome = self.apply_names(
+                NamesUpdate {
+                    chunk: chunk.to_vec(),
+                    result,
+                },
+                now,
             );
-            let Ok(mut response) = ureq::get(&url).call() else {
-                //TODO let's figure out how to handle that later
-                continue;
+            if bad_key {
+                self.save_names();
+                return Err(SteamError::BadKey);
+            }
+            summary.chunks.push(outcome);
+        }
+
+        if summary.succeeded().next().is_some() {
+            self.save_names();
+        }
+        Ok(summary)
+    }
+
+    /// The ids [`SteamApi::add_ids`] has to fetch, and a summary counting the others.
+    fn stale_ids(&self, id_list: &[SteamId], now: DateTime<Utc>) -> (Vec<SteamId>, FetchSummary) {
+        let (players, spoofed): (Vec<SteamId>, Vec<SteamId>) =
+            id_list.iter().partition(|id| id.is_player());
+        let stale = self.names.stale_ids(&players, self.ttl, now);
+        let summary = FetchSummary {
+            cached: players.len() - stale.len(),
+            spoofed: spoofed.len(),
+            ..Default::default()
+        };
+        (stale, summary)
+    }
+
+    fn apply_names(&mut self, update: NamesUpdate, now: DateTime<Utc>) -> ChunkOutcome {
+        let NamesUpdate { chunk, result } = update;
+        let players = match result {
+            Ok(players) => players,
+            Err(e) => {
+                self.diagnostics.error(
+                    e.kind(),
+                    None,
+                    format!("GetPlayerSummaries for {} ids: {e}", chunk.len()),
+                );
+                return ChunkOutcome {
+                    ids: chunk,
+                    error: Some(e),
+                };
+            }
+        };
+        let mut recv_ids = HashSet::new();
+        for player in players {
+            if let Some(id) = player.id() {
+                let avatars = player.avatars();
+                self.names.insert(id, player.personaname, now);
+                if let Some(avatars) = avatars {
+                    self.names.set_avatars(id, avatars);
+                }
+                recv_ids.insert(id);
+            }
+        }
+
+        // Have to do this since the order of returned players isn't static
+        let a: HashSet<SteamId> = HashSet::from_iter(chunk.iter().cloned());
+        let difference = a.difference(&recv_ids).collect::<Vec<&SteamId>>();
+        for diff in difference {
+            self.names.insert_missing(*diff, "Unknown/Spoofed", now);
+        }
+        ChunkOutcome {
+            ids: chunk,
+            error: None,
+        }
+    }
+
+    /// Downloads the avatars of `ids` that aren't on disk yet, returning how many were.
+    ///
+    /// Files are named after the URL, which changes with the image, so players sharing
+    /// an avatar share the file and a new avatar never shows a stale one.
+    pub fn fetch_avatars(&mut self, ids: &[SteamId]) -> usize {
+        let requester = self.requester();
+        let updates: Vec<AvatarUpdate> = self
+            .avatar_jobs(ids)
+            .into_iter()
+            .map(|job| job.run(&requester))
+            .collect();
+        updates
+            .into_iter()
+            .filter(|update| self.apply_avatar(update))
+            .count()
+    }
+
+    /// [`SteamApi::add_ids`] then [`SteamApi::fetch_avatars`] on another thread, calling
+    /// `on_arrival` after each request. Nothing changes until [`SteamApi::poll`] picks
+    /// them up. Names are skipped without a key, cached avatars still show up.
+    pub fn fetch_in_background(&mut self, ids: &[SteamId], on_arrival: impl Fn() + Send + 'static) {
+        let (stale, summary) = self.stale_ids(ids, Utc::now());
+        log::info!(
+            "Fetching {} names in the background, {} ids cached, {} ids spoofed",
+            stale.len(),
+            summary.cached,
+            summary.spoofed
+        );
+        let stale = if self.api_key.is_empty() {
+            Vec::new()
+        } else {
+            stale
+        };
+        let dir = self.avatar_dir.clone();
+        let mut urls: Vec<(SteamId, Option<AvatarUrls>)> = ids
+            .iter()
+            .map(|&id| (id, self.names.get(id).and_then(|c| c.avatars.clone())))
+            .collect();
+        let requester = self.requester();
+        let sender = self.sender();
+        thread::spawn(move || {
+            let send = |update| {
+                let sent = sender.send(update).is_ok();
+                if sent {
+                    on_arrival();
+                }
+                sent
             };
-            let Ok(response) = response.body_mut().read_json::<SteamApiResponse>() else {
-                //TODO let's figure out how to handle that later
-                continue;
+            for chunk in stale.chunks(100) {
+                let result = requester.player_summaries(chunk);
+                let bad_key = matches!(result, Err(SteamError::BadKey));
+                // newer avatars than the cached ones, so they're downloaded below
+                for player in result.iter().flatten() {
+                    if let (Some(id), Some(avatars)) = (player.id(), player.avatars()) {
+                        for (_, cached) in urls.iter_mut().filter(|(i, _)| *i == id) {
+                            *cached = Some(avatars.clone());
+                        }
+                    }
+                }
+                let update = NamesUpdate {
+                    chunk: chunk.to_vec(),
+                    result,
+                };
+                if !send(Update::Names(update)) || bad_key {
+                    break;
+                }
+            }
+            let Some(dir) = dir else {
+                return;
             };
-            let players = response.response.players;
-            let mut recv_ids = HashSet::new();
-            for player in players {
-                if let Ok(id) = player.steamid.parse::<u64>() {
-                    self.ids.insert(id, player.personaname);
-                    recv_ids.insert(id);
+            let urls = urls
+                .into_iter()
+                .filter_map(|(id, urls)| urls.map(|urls| (id, urls)));
+            for job in avatar_jobs(&dir, urls) {
+                if !send(Update::Avatar(job.run(&requester))) {
+                    return;
+                }
+            }
+        });
+    }
+
+    /// [`SteamApi::fetch_avatars`] on another thread, see [`SteamApi::fetch_in_background`].
+    pub fn fetch_avatars_in_background(
+        &mut self,
+        ids: &[SteamId],
+        on_arrival: impl Fn() + Send + 'static,
+    ) {
+        let jobs = self.avatar_jobs(ids);
+        let requester = self.requester();
+        let sender = self.sender();
+        thread::spawn(move || {
+            for job in jobs {
+                if sender.send(Update::Avatar(job.run(&requester))).is_err() {
+                    return;
+                }
+                on_arrival();
+            }
+        });
+    }
+
+    /// [`SteamApi::friend_list`] on another thread, errors end up in the diagnostics
+    /// once [`SteamApi::poll`] picks it up.
+    pub fn fetch_friends_in_background(
+        &mut self,
+        steam_id: SteamId,
+        on_arrival: impl Fn() + Send + 'static,
+    ) {
+        let requester = self.requester();
+        let sender = self.sender();
+        thread::spawn(move || {
+            let friends = requester.friend_list(steam_id);
+            if sender.send(Update::Friends(steam_id, friends)).is_ok() {
+                on_arrival();
+            }
+        });
+    }
+
+    fn sender(&mut self) -> Sender<Update> {
+        self.sender
+            .get_or_insert_with(|| {
+                let (sender, receiver) = mpsc::channel();
+                self.updates = Some(receiver);
+                sender
+            })
+            .clone()
+    }
+
+    /// Takes in what the background fetch got so far.
+    pub fn poll(&mut self) -> Polled {
+        let Some(receiver) = &self.updates else {
+            return Polled::default();
+        };
+        let updates: Vec<Update> = receiver.try_iter().collect();
+        let mut polled = Polled::default();
+        let mut fetched_names = false;
+        for update in updates {
+            match update {
+                Update::Names(update) => {
+                    if let Ok(players) = &update.result {
+                        polled.names += players.len();
+                    }
+                    fetched_names |= self.apply_names(update, Utc::now()).error.is_none();
+                }
+                Update::Avatar(update) => {
+                    if self.apply_avatar(&update) {
+                        polled.avatars += 1;
+                    }
                 }
+                Update::Friends(steam_id, friends) => match friends {
+                    Ok(friends) => polled.friends = Some(friends),
+                    Err(e) => self.report_friend_list(steam_id, &e),
+                },
             }
+        }
+        if fetched_names {
+            self.save_names();
+        }
+        polled
+    }
 
-            // Have to do this since the order of returned players isn't static
-            let a: HashSet<u64> = HashSet::from_iter(chunk.iter().cloned());
-            let difference = a.difference(&recv_ids).collect::<Vec<&u64>>();
-            for diff in difference {
-                self.ids.insert(*diff, String::from("Unknown/Spoofed"));
+    fn avatar_jobs(&self, ids: &[SteamId]) -> Vec<AvatarJob> {
+        let Some(dir) = &self.avatar_dir else {
+            return Vec::new();
+        };
+        avatar_jobs(
+            dir,
+            ids.iter()
+                .filter_map(|&id| Some((id, self.names.get(id)?.avatars.clone()?))),
+        )
+    }
+
+    /// Returns whether the avatar was downloaded.
+    fn apply_avatar(&mut self, update: &AvatarUpdate) -> bool {
+        let AvatarJob { id, size, path, .. } = &update.job;
+        match &update.result {
+            Ok(downloaded) => {
+                self.avatars.insert((*id, *size), path.clone());
+                *downloaded
+            }
+            Err((kind, e)) => {
+                self.diagnostics
+                    .error(*kind, Some(path.clone()), format!("Avatar of {id}: {e}"));
+                false
             }
+        }
+    }
+
+    /// The cached avatar image, once [`SteamApi::fetch_avatars`] got it.
+    pub fn avatar(&self, id: SteamId, size: AvatarSize) -> Option<&Path> {
+        self.avatars.get(&(id, size)).map(PathBuf::as_path)
+    }
 
-            //TODO not urgent but some throttling measure here/in the struct?
+    /// The friends of `steam_id`, which needs their friend list to be public.
+    pub fn friend_list(&mut self, steam_id: SteamId) -> Result<Vec<SteamId>, SteamError> {
+        self.requester()
+            .friend_list(steam_id)
+            .inspect_err(|e| self.report_friend_list(steam_id, e))
+    }
+
+    /// A private list is up to its owner, only warn about that one.
+    fn report_friend_list(&mut self, steam_id: SteamId, e: &SteamError) {
+        let message = format!("GetFriendList for {steam_id}: {e}");
+        match e {
+            SteamError::Private => self.diagnostics.warn(e.kind(), None, message),
+            _ => self.diagnostics.error(e.kind(), None, message),
         }
     }
 
-    pub fn get(&self, id: u64) -> &str {
-        if let Some(player) = self.ids.get(&id) {
-            player
-        } else {
-            //TODO let's figure out how to handle that later
-            "Unknown/Unparsed"
+    fn requester(&self) -> Requester {
+        Requester {
+            agent: agent(),
+            throttle: self.throttle.clone(),
+            retry: self.retry.clone(),
+            api_key: self.api_key.clone(),
+            base_url: self.base_url.clone(),
         }
     }
+
+    fn save_names(&mut self) {
+        if let Err(e) = self.names.save() {
+            self.diagnostics.error(
+                DiagnosticKind::Io,
+                self.names.path().map(|p| p.to_path_buf()),
+                format!("Saving the name cache: {e}"),
+            );
+        }
+    }
+
+    /// The cached persona, `None` if it was never fetched.
+    pub fn name(&self, id: SteamId) -> Option<&str> {
+        if !id.is_player() {
+            return Some("Unknown/Spoofed");
+        }
+        self.names.get(id).map(|player| player.name.as_str())
+    }
+
+    pub fn cached(&self, id: SteamId) -> Option<&CachedName> {
+        self.names.get(id)
+    }
+
+    pub fn get(&self, id: SteamId) -> &str {
+        //TODO let's figure out how to handle that later
+        self.name(id).unwrap_or("Unknown/Unparsed")
+    }
+}
+
+fn avatar_jobs(
+    dir: &Path,
+    urls: impl IntoIterator<Item = (SteamId, AvatarUrls)>,
+) -> Vec<AvatarJob> {
+    let mut jobs = Vec::new();
+    for (id, urls) in urls {
+        for (size, url) in [
+            (AvatarSize::Small, urls.small),
+            (AvatarSize::Medium, urls.medium),
+        ] {
+            let Some(file) = url.rsplit('/').next().filter(|f| {
+                !f.is_empty()
+                    && f.chars()
+                        .all(|c| c.is_ascii_alphanumeric() || "._-".contains(c))
+            }) else {
+                continue;
+            };
+            let path = dir.join(file);
+            jobs.push(AvatarJob {
+                id,
+                size,
+                url,
+                path,
+            });
+        }
+    }
+    jobs
+}
+
+fn agent() -> Agent {
+    Agent::config_builder()
+        .http_status_as_error(false)
+        .timeout_global(Some(Duration::from_secs(30)))
+        .build()
+        .into()
+}
+
+/// `Retry-After` in seconds, the HTTP-date form isn't used by Steam.
+fn retry_after<B>(response: &HttpResponse<B>) -> Option<Duration> {
+    response
+        .headers()
+        .get("retry-after")?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
 }
 
 #[cfg(test)]
@@ -88,9 +819,47 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_steam_api() {
-        let api_key = env::var("APIKEY").unwrap(); //TODO changeme
-        let mut s = SteamApi::new(api_key);
-        s.add_ids(&[76561197960435530, 0101, 76561198001860904]);
+    fn test_token_bucket() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(2, 1.0);
+        bucket.last = start;
+        assert_eq!(bucket.reserve(start), Duration::ZERO);
+        assert_eq!(bucket.reserve(start), Duration::ZERO);
+        assert_eq!(bucket.reserve(start), Duration::from_secs(1));
+        assert_eq!(bucket.reserve(start), Duration::from_secs(2));
+        // refills, but the two queued requests have to be paid back first
+        assert_eq!(
+            bucket.reserve(start + Duration::from_secs(10)),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        let retry = RetryPolicy {
+            max_retries: 10,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(500),
+        };
+        assert_eq!(retry.delay(0), Duration::from_millis(100));
+        assert_eq!(retry.delay(2), Duration::from_millis(400));
+        assert_eq!(retry.delay(3), Duration::from_millis(500));
+        assert_eq!(retry.delay(40), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_redact() {
+        let api = SteamApi::new(String::from("SECRET"));
+        assert_eq!(api.base_url, DEFAULT_BASE_URL);
+        assert_eq!(
+            api.requester().redact("/v0002/?key=SECRET&steamids=1"),
+            "/v0002/?key=<key>&steamids=1"
+        );
+        assert_eq!(
+            SteamApi::new(String::new())
+                .with_base_url("http://localhost:1234/")
+                .base_url,
+            "http://localhost:1234"
+        );
     }
 }
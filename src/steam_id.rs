@@ -0,0 +1,227 @@
+//! SteamID64 layout: account id in the low 32 bits, then a 20 bit instance,
+//! a 4 bit account type and an 8 bit universe.
+
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+const ACCOUNT_TYPE_INDIVIDUAL: u8 = 1;
+const UNIVERSE_PUBLIC: u8 = 1;
+const INSTANCE_DESKTOP: u32 = 1;
+
+/// Letters of the `[X:1:n]` format, by account type.
+const TYPE_LETTERS: [char; 11] = ['I', 'U', 'M', 'G', 'A', 'P', 'C', 'g', 'T', ' ', 'a'];
+
+/// A SteamID64 as read from a replay, not necessarily a valid one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SteamId(u64);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SteamIdError {
+    Universe(u8),
+    AccountType(u8),
+    /// Individual accounts start at 1.
+    AccountId,
+    Parse(String),
+}
+
+impl fmt::Display for SteamIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SteamIdError::Universe(universe) => write!(f, "Invalid universe {universe}"),
+            SteamIdError::AccountType(kind) => write!(f, "Invalid account type {kind}"),
+            SteamIdError::AccountId => write!(f, "Account id 0"),
+            SteamIdError::Parse(text) => write!(f, "Not a Steam ID: {text:?}"),
+        }
+    }
+}
+
+impl std::error::Error for SteamIdError {}
+
+/// How Steam IDs are shown to the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum SteamIdFormat {
+    /// `76561197960435530`
+    #[default]
+    Id64,
+    /// The account id, `169802`
+    Id32,
+    /// `[U:1:169802]`
+    Id3,
+}
+
+impl SteamId {
+    /// Checks the universe, account type and account id bits.
+    pub fn new(id64: u64) -> Result<Self, SteamIdError> {
+        let id = Self(id64);
+        if !(1..=4).contains(&id.universe()) {
+            return Err(SteamIdError::Universe(id.universe()));
+        }
+        if !(1..=10).contains(&id.account_type()) || id.account_type() == 9 {
+            return Err(SteamIdError::AccountType(id.account_type()));
+        }
+        if id.account_type() == ACCOUNT_TYPE_INDIVIDUAL && id.account_id() == 0 {
+            return Err(SteamIdError::AccountId);
+        }
+        Ok(id)
+    }
+
+    /// The public individual account behind a SteamID32, like the ones in `userdata/`.
+    pub const fn from_account_id(account_id: u32) -> Self {
+        Self(
+            (UNIVERSE_PUBLIC as u64) << 56
+                | (ACCOUNT_TYPE_INDIVIDUAL as u64) << 52
+                | (INSTANCE_DESKTOP as u64) << 32
+                | account_id as u64,
+        )
+    }
+
+    pub const fn as_u64(self) -> u64 {
+        self.0
+    }
+
+    pub const fn account_id(self) -> u32 {
+        self.0 as u32
+    }
+
+    pub const fn instance(self) -> u32 {
+        (self.0 >> 32) as u32 & 0xF_FFFF
+    }
+
+    pub const fn account_type(self) -> u8 {
+        (self.0 >> 52) as u8 & 0xF
+    }
+
+    pub const fn universe(self) -> u8 {
+        (self.0 >> 56) as u8
+    }
+
+    pub fn is_valid(self) -> bool {
+        Self::new(self.0).is_ok()
+    }
+
+    /// Whether this can be someone who played a replay, anything else was spoofed or corrupted.
+    pub fn is_player(self) -> bool {
+        self.is_valid()
+            && self.universe() == UNIVERSE_PUBLIC
+            && self.account_type() == ACCOUNT_TYPE_INDIVIDUAL
+    }
+
+    pub fn display(self, format: SteamIdFormat) -> impl fmt::Display {
+        SteamIdDisplay(self, format)
+    }
+}
+
+impl From<u64> for SteamId {
+    /// Keeps the raw value, invalid ids included, see [`SteamId::is_valid`].
+    fn from(id64: u64) -> Self {
+        Self(id64)
+    }
+}
+
+impl fmt::Display for SteamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+struct SteamIdDisplay(SteamId, SteamIdFormat);
+
+impl fmt::Display for SteamIdDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let SteamIdDisplay(id, format) = *self;
+        match format {
+            SteamIdFormat::Id64 => write!(f, "{id}"),
+            SteamIdFormat::Id32 => write!(f, "{}", id.account_id()),
+            SteamIdFormat::Id3 => {
+                let letter = TYPE_LETTERS
+                    .get(id.account_type() as usize)
+                    .copied()
+                    .unwrap_or('I');
+                write!(f, "[{letter}:{}:{}]", id.universe(), id.account_id())
+            }
+        }
+    }
+}
+
+impl FromStr for SteamId {
+    type Err = SteamIdError;
+
+    /// Takes any of the [`SteamIdFormat`]s, numbers that fit in 32 bits are account ids.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let parse_error = || SteamIdError::Parse(text.to_string());
+        let trimmed = text.trim();
+        if let Some(id3) = trimmed
+            .strip_prefix('[')
+            .and_then(|t| t.strip_suffix(']'))
+            .or_else(|| trimmed.strip_prefix("U:"))
+            .map(|t| t.strip_prefix("U:").unwrap_or(t))
+        {
+            let (universe, account_id) = id3.split_once(':').ok_or_else(parse_error)?;
+            let universe: u8 = universe.parse().map_err(|_| parse_error())?;
+            if universe != UNIVERSE_PUBLIC {
+                return Err(SteamIdError::Universe(universe));
+            }
+            let account_id: u32 = account_id.parse().map_err(|_| parse_error())?;
+            return Self::new(Self::from_account_id(account_id).0);
+        }
+        let number: u64 = trimmed.parse().map_err(|_| parse_error())?;
+        match u32::try_from(number) {
+            Ok(account_id) => Self::new(Self::from_account_id(account_id).0),
+            Err(_) => Self::new(number),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        let robin = SteamId::new(76561197960435530).unwrap();
+        assert_eq!(robin, SteamId::from_account_id(169802));
+        assert_eq!(robin.account_id(), 169802);
+        assert_eq!(robin.instance(), 1);
+        assert!(robin.is_player());
+        assert_eq!(
+            robin.display(SteamIdFormat::Id64).to_string(),
+            "76561197960435530"
+        );
+        assert_eq!(robin.display(SteamIdFormat::Id32).to_string(), "169802");
+        assert_eq!(
+            robin.display(SteamIdFormat::Id3).to_string(),
+            "[U:1:169802]"
+        );
+        for text in [
+            "76561197960435530",
+            "169802",
+            "[U:1:169802]",
+            " U:1:169802 ",
+        ] {
+            assert_eq!(text.parse::<SteamId>(), Ok(robin), "{text}");
+        }
+    }
+
+    #[test]
+    #[allow(clippy::zero_prefixed_literal)]
+    fn test_invalid() {
+        let spoofed = SteamId::from(0101);
+        assert_eq!(SteamId::new(0101), Err(SteamIdError::Universe(0)));
+        assert!(!spoofed.is_player());
+        assert_eq!(
+            SteamId::new(0x0110_0001_0000_0000),
+            Err(SteamIdError::AccountId)
+        );
+        assert_eq!(
+            SteamId::new(0x01F0_0001_0000_0001),
+            Err(SteamIdError::AccountType(15))
+        );
+        // a valid game server isn't someone who can play
+        assert!(!SteamId::new(0x0130_0000_0000_0001).unwrap().is_player());
+        assert!("[U:2:1]".parse::<SteamId>().is_err());
+        assert!("Robin".parse::<SteamId>().is_err());
+        assert!("[U:1:0]".parse::<SteamId>().is_err());
+    }
+}
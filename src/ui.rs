@@ -1,11 +1,24 @@
-use std::{collections::HashSet, env};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    path::Path,
+};
 
-use egui::TextWrapMode;
+use chrono::{DateTime, Datelike, Local, NaiveDate, TimeDelta, Utc};
 use egui_extras::{Column, TableBuilder};
+use log::Level;
 
 use crate::{
-    replay::{KonohaDifficulty, Mode, ReplayStore},
-    steam::SteamApi,
+    charts::{self, ChartFilter},
+    config::Config,
+    daily::{challenges, participation, streaks},
+    diagnostics::{Diagnostic, Diagnostics},
+    friends::{Friends, PlayerFilter},
+    name_cache::{CachedName, NameCache},
+    names::{Aliases, LocalSteam, NameChain, NameResolver},
+    replay::{KonohaDifficulty, Mod, Mode, Replay, ReplayStore},
+    stats::{HeadToHead, PlayerStats, head_to_head, personal_bests, same_seed, sessions},
+    steam::{AvatarSize, SteamApi},
+    steam_id::{SteamId, SteamIdFormat},
 };
 
 pub struct ManagerUI {
@@ -13,7 +26,21 @@ pub struct ManagerUI {
     selected_mode: Mode,
     selected_rows: SelectedRows,
     replay_store: ReplayStore,
-    steam_api: SteamApi,
+    names: NameChain,
+    /// Alias being typed in a Name cell's context menu.
+    alias_edit: Option<(SteamId, String)>,
+    /// Last clicked replay, shown in the side panel.
+    detail: Option<Replay>,
+    /// Player whose page is open.
+    player: Option<SteamId>,
+    /// Seed whose replays are being compared.
+    seed: Option<u32>,
+    steam_id_format: SteamIdFormat,
+    friends: Friends,
+    player_filter: PlayerFilter,
+    chart: ChartFilter,
+    session_gap: TimeDelta,
+    show_log: bool,
 }
 
 #[derive(serde::Deserialize, Default)]
@@ -32,6 +59,10 @@ enum Tab {
     Game,
     Backup,
     Tap,
+    Bests,
+    Progress,
+    Sessions,
+    Daily,
 }
 
 impl Default for ManagerUI {
@@ -39,9 +70,19 @@ impl Default for ManagerUI {
         Self {
             selected_tab: Tab::Game,
             selected_mode: Mode::Normal,
-            steam_api: Default::default(),
+            names: Default::default(),
             replay_store: Default::default(),
             selected_rows: Default::default(),
+            alias_edit: None,
+            detail: None,
+            player: None,
+            seed: None,
+            steam_id_format: SteamIdFormat::default(),
+            friends: Default::default(),
+            player_filter: PlayerFilter::default(),
+            chart: ChartFilter::default(),
+            session_gap: Config::default().session_gap(),
+            show_log: false,
         }
     }
 }
@@ -50,26 +91,292 @@ impl ManagerUI {
     pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
         // This is also where you can customize the look and feel of egui using
         // `cc.egui_ctx.set_visuals` and `cc.egui_ctx.set_fonts`.
-        let api_key = env::var("APIKEY").unwrap_or_default(); //TODO changeme
+        egui_extras::install_image_loaders(&cc.egui_ctx);
+        let config = Config::load();
+        let session_gap = config.session_gap();
         let replay_store = ReplayStore::new();
-        let mut steam_api = SteamApi::new(api_key);
+        let mut steam_api =
+            SteamApi::new(config.api_key.clone()).with_base_url(&config.steam_api_base_url);
+        if let Some(path) = NameCache::default_path() {
+            steam_api = steam_api.with_cache(
+                NameCache::load(path),
+                TimeDelta::hours(config.name_cache_ttl_hours as i64),
+            );
+        }
         let ids = replay_store.get_steam_ids();
         dbg!(&ids);
-        steam_api.add_ids(&ids);
+        // Without a key every request would be refused, the other resolvers still work
+        if config.api_key.is_empty() {
+            log::info!("No Steam Web API key, only using local names and aliases");
+        }
+        if let Some(dir) = SteamApi::default_avatar_dir() {
+            steam_api = steam_api.with_avatar_dir(dir);
+        }
+        // Names then avatars, also picks up avatars downloaded by previous runs.
+        // They pop in as they arrive, so startup doesn't wait on the Web API
+        let ctx = cc.egui_ctx.clone();
+        steam_api.fetch_in_background(&ids, move || ctx.request_repaint());
+        let mut names = NameChain {
+            aliases: Aliases::default_path()
+                .map(Aliases::load)
+                .unwrap_or_default(),
+            local: config
+                .steam_path
+                .or_else(LocalSteam::default_root)
+                .map(|root| LocalSteam::load(&root))
+                .unwrap_or_default(),
+            steam_api,
+        };
+        // The client's cache shows right away, the Web API's list replaces it if it's public
+        let friends = match config.my_steam_id.or(names.local.most_recent_user()) {
+            Some(me) => {
+                if !config.api_key.is_empty() {
+                    let ctx = cc.egui_ctx.clone();
+                    names
+                        .steam_api
+                        .fetch_friends_in_background(me, move || ctx.request_repaint());
+                }
+                Friends::new(Some(me), names.local.friends_of(me).to_vec())
+            }
+            None => Friends::default(),
+        };
+        log::info!("{} friends of {:?}", friends.len(), friends.me);
 
         Self {
             replay_store,
-            steam_api,
+            names,
+            friends,
+            steam_id_format: config.steam_id_format,
+            session_gap,
             ..Default::default()
         }
     }
 
+    fn show_progress(&mut self, ui: &mut egui::Ui) {
+        let Some(me) = self.friends.me else {
+            ui.label("Set my_steam_id in the config or log into Steam to see your progress.");
+            return;
+        };
+        let mine = self.replay_store.all().filter(|r| r.steamid == me);
+        charts::show(ui, &mut self.chart, mine);
+    }
+
+    fn show_sessions(&mut self, ui: &mut egui::Ui) {
+        let Some(me) = self.friends.me else {
+            ui.label("Set my_steam_id in the config or log into Steam to see your sessions.");
+            return;
+        };
+        let sessions = sessions(me, self.replay_store.all(), self.session_gap);
+        if sessions.is_empty() {
+            ui.label("No replays of yours yet.");
+            return;
+        }
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for session in sessions.iter().rev() {
+                let start = session.start();
+                let header = format!(
+                    "{} to {}, {} games, {} played",
+                    start.format("%Y-%m-%d %H:%M"),
+                    session.end().format("%H:%M"),
+                    session.replays.len(),
+                    session.playtime()
+                );
+                egui::CollapsingHeader::new(header)
+                    .id_salt(start.timestamp())
+                    .show(ui, |ui| {
+                        egui::Grid::new(("session", start.timestamp()))
+                            .num_columns(6)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for header in
+                                    ["Mode", "Rule", "Level", "Score", "Time", "Since last time"]
+                                {
+                                    ui.strong(header);
+                                }
+                                ui.end_row();
+                                for (key, best) in &session.bests {
+                                    let (mode, rule) = key;
+                                    ui.label(mode);
+                                    ui.label(rule.to_string());
+                                    ui.label(best.level.to_string());
+                                    ui.label(best.score.to_string());
+                                    if ui.link(best.time.to_string()).clicked() {
+                                        self.detail = Some((*best).clone());
+                                    }
+                                    ui.label(
+                                        session
+                                            .progress
+                                            .get(key)
+                                            .map(|p| p.to_string())
+                                            .unwrap_or_else(|| String::from("First time")),
+                                    );
+                                    ui.end_row();
+                                }
+                            });
+                    });
+            }
+        });
+    }
+
+    fn show_daily(&mut self, ui: &mut egui::Ui) {
+        let challenges = challenges(self.replay_store.all());
+        if challenges.is_empty() {
+            ui.label("No Daily challenge replays yet.");
+            return;
+        }
+        if let Some(me) = self.friends.me {
+            let days = participation(me, self.replay_store.all());
+            let today = Local::now().date_naive();
+            let streaks = streaks(&days.keys().copied().collect::<BTreeSet<_>>(), today);
+            ui.label(format!(
+                "Played {} days, current streak {}, longest {}",
+                days.len(),
+                streaks.current,
+                streaks.longest
+            ));
+            show_heatmap(ui, &days, today);
+            ui.separator();
+        }
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for challenge in challenges.iter().rev() {
+                let mut header = format!(
+                    "{} {} ({}), seed {}, {} players",
+                    challenge.day,
+                    challenge.mode,
+                    challenge.rule,
+                    challenge.seed,
+                    challenge.ranking.len()
+                );
+                if let Some(rank) = self.friends.me.and_then(|me| challenge.rank_of(me)) {
+                    header += &format!(", you placed #{rank}");
+                }
+                let id = (challenge.day, challenge.seed);
+                ui.horizontal(|ui| {
+                    egui::CollapsingHeader::new(header)
+                        .id_salt(id)
+                        .show(ui, |ui| {
+                            egui::Grid::new(("daily", id))
+                                .num_columns(5)
+                                .striped(true)
+                                .show(ui, |ui| {
+                                    for header in ["#", "Name", "Level", "Score", "Time"] {
+                                        ui.strong(header);
+                                    }
+                                    ui.end_row();
+                                    for (i, replay) in challenge.ranking.iter().enumerate() {
+                                        ui.label((i + 1).to_string());
+                                        if ui.link(self.names.get(replay.steamid)).clicked() {
+                                            self.player = Some(replay.steamid);
+                                        }
+                                        ui.label(replay.level.to_string());
+                                        ui.label(replay.score.to_string());
+                                        if ui.link(replay.time.to_string()).clicked() {
+                                            self.detail = Some((*replay).clone());
+                                        }
+                                        ui.end_row();
+                                    }
+                                });
+                        });
+                    if !challenge.same_seed_days.is_empty() {
+                        let days: Vec<String> = challenge
+                            .same_seed_days
+                            .iter()
+                            .map(|d| d.to_string())
+                            .collect();
+                        ui.label("🔗").on_hover_text(format!(
+                            "Same challenge as the replays of {}",
+                            days.join(", ")
+                        ));
+                    }
+                });
+            }
+        });
+    }
+
+    fn show_bests(&mut self, ui: &mut egui::Ui) {
+        let Some(me) = self.friends.me else {
+            ui.label("Set my_steam_id in the config or log into Steam to see your personal bests.");
+            return;
+        };
+        let bests = personal_bests(me, self.replay_store.all());
+        if bests.is_empty() {
+            ui.label("No replays of yours yet.");
+            return;
+        }
+        let text_height = egui::TextStyle::Body
+            .resolve(ui.style())
+            .size
+            .max(ui.spacing().interact_size.y);
+
+        TableBuilder::new(ui)
+            .resizable(true)
+            .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+            .striped(true)
+            .columns(Column::auto(), 7)
+            .column(Column::remainder())
+            .min_scrolled_height(0.0)
+            .header(20.0, |mut header| {
+                for title in [
+                    "Mode", "Rule", "Options", "Level", "Score", "Playtime", "Set on", "Margin",
+                ] {
+                    header.col(|ui| {
+                        ui.strong(title);
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(text_height, bests.len(), |mut row| {
+                    let pb = &bests[row.index()];
+                    row.col(|ui| {
+                        ui.label(pb.mode.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(pb.rule.to_string());
+                    });
+                    row.col(|ui| {
+                        let modifiers = pb.best.modifiers.iter().map(|m| m.to_string());
+                        ui.label(modifiers.collect::<Vec<_>>().join(", "));
+                    });
+                    row.col(|ui| {
+                        ui.label(pb.best.level.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(pb.best.score.to_string());
+                    });
+                    row.col(|ui| {
+                        ui.label(pb.best.time.to_string());
+                    });
+                    row.col(|ui| {
+                        let date = pb.best.played_at.format("%Y-%m-%d %H:%M:%S");
+                        if ui.link(date.to_string()).clicked() {
+                            self.detail = Some(pb.best.clone());
+                        }
+                    });
+                    row.col(|ui| match pb.margin() {
+                        Some(margin) => {
+                            ui.label(margin.to_string()).on_hover_text(format!(
+                                "Previous best on {}",
+                                pb.previous.unwrap().played_at.format("%Y-%m-%d")
+                            ));
+                        }
+                        None => {
+                            ui.label("First");
+                        }
+                    });
+                });
+            });
+    }
+
     fn show_table(&mut self, ui: &mut egui::Ui) {
         let text_height = egui::TextStyle::Body
             .resolve(ui.style())
             .size
             .max(ui.spacing().interact_size.y);
 
+        let detail = &mut self.detail;
+        let player = &mut self.player;
+        let seed = &mut self.seed;
         let (replays, selected_rows) = match self.selected_mode {
             Mode::Marathon => (
                 &self.replay_store.marathon,
@@ -86,7 +393,14 @@ impl ManagerUI {
             Mode::Versus => (&self.replay_store.pvp, &mut self.selected_rows.pvp),
         };
 
-        let mut table = TableBuilder::new(ui)
+        let visible: Vec<usize> = replays
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| self.player_filter.matches(self.friends.relation(r.steamid)))
+            .map(|(i, _)| i)
+            .collect();
+
+        TableBuilder::new(ui)
             .resizable(true)
             .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
             .striped(true)
@@ -132,9 +446,9 @@ impl ManagerUI {
                     ui.strong("Date");
                 });
             })
-            .body(|mut body| {
-                body.rows(text_height, replays.len(), |mut row| {
-                    let row_index = row.index();
+            .body(|body| {
+                body.rows(text_height, visible.len(), |mut row| {
+                    let row_index = visible[row.index()];
                     row.set_selected(selected_rows.contains(&row_index));
                     // Useless in Normal/Marathon
                     // self.replay_store.normal.get(row_index).map(|replay| {
@@ -143,9 +457,35 @@ impl ManagerUI {
                     if let Some(replay) = replays.get(row_index) {
                         row.col(|ui| {
                             ui.label(row_index.to_string());
+                            if replay.is_corrupted() {
+                                ui.colored_label(ui.visuals().warn_fg_color, "⚠")
+                                    .on_hover_text(replay.integrity.to_string());
+                            } else if !replay.is_intact() {
+                                ui.weak("?").on_hover_text(replay.integrity.to_string());
+                            }
                         });
                         row.col(|ui| {
-                            ui.label(self.steam_api.get(replay.steamid));
+                            let id = replay.steamid;
+                            if let Some(path) = self.names.steam_api.avatar(id, AvatarSize::Small) {
+                                ui.add(avatar_image(path, text_height));
+                            }
+                            let mut response = ui.link(self.names.get(id));
+                            if self.names.aliases.resolve(id).is_some() {
+                                response = response
+                                    .on_hover_text(format!("Steam: {}", self.names.persona(id)));
+                            }
+                            if response.clicked() {
+                                *player = Some(id);
+                            }
+                            response.context_menu(|ui| {
+                                alias_menu(
+                                    ui,
+                                    &mut self.names,
+                                    &mut self.alias_edit,
+                                    id,
+                                    self.steam_id_format,
+                                )
+                            });
                         });
                         if self.selected_mode != Mode::Normal
                             && self.selected_mode != Mode::Marathon
@@ -158,31 +498,46 @@ impl ManagerUI {
                             ui.label(replay.level.to_string());
                         });
                         row.col(|ui| {
-                            ui.label("TODO");
-                            //                            ui.label(replay.modifiers.to_string());
+                            let known = replay
+                                .modifiers
+                                .iter()
+                                .filter(|m| !matches!(m, Mod::Unknown(_)))
+                                .map(|m| m.to_string())
+                                .collect::<Vec<_>>()
+                                .join(", ");
+                            ui.label(known);
+                            if let Some(unknown) = replay.unknown_modifiers() {
+                                ui.colored_label(ui.visuals().warn_fg_color, "?")
+                                    .on_hover_text(format!(
+                                        "{unknown}, raw byte {:#010b}",
+                                        replay.modifier_byte
+                                    ));
+                            }
                         });
                         row.col(|ui| {
-                            //TODO formatting function I missed somewhere?
-                            let secs = replay.time.as_secs();
-                            let millis = replay.time.subsec_millis();
-                            let (first, second) = (millis / 100, millis % 10);
-                            let mins = secs / 60;
-                            let secs = secs % 60;
-                            ui.label(format!("{mins:0>2}'{secs:0>2}\"{first}{second}"));
-                            //ui.label(replay.time.format());
+                            ui.label(replay.time.to_string());
                         });
                         row.col(|ui| {
                             ui.label(replay.score.to_string());
                         });
                         row.col(|ui| {
-                            ui.label(replay.seed.to_string());
+                            if ui
+                                .link(replay.seed.to_string())
+                                .on_hover_text("Find replays with this seed")
+                                .clicked()
+                            {
+                                *seed = Some(replay.seed);
+                            }
                         });
                         row.col(|ui| {
                             ui.label(replay.played_at.format("%Y-%m-%d %H:%M:%S").to_string());
                         });
                     }
 
-                    toggle_row_selection(selected_rows, row_index, &row.response());
+                    if row.response().clicked() {
+                        toggle_row_selection(selected_rows, row_index);
+                        *detail = replays.get(row_index).cloned();
+                    }
                 });
             });
         //.max_scroll_height(400);
@@ -190,6 +545,21 @@ impl ManagerUI {
 }
 impl eframe::App for ManagerUI {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let polled = self.names.steam_api.poll();
+        if polled.names > 0 {
+            log::info!("Fetched {} names", polled.names);
+        }
+        if polled.avatars > 0 {
+            log::info!("Downloaded {} avatars", polled.avatars);
+        }
+        if let Some(listed) = polled.friends {
+            self.friends = Friends::new(self.friends.me, listed);
+            log::info!(
+                "{} friends of {:?} from the Web API",
+                self.friends.len(),
+                self.friends.me
+            );
+        }
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 ui.menu_button("File", |ui| {
@@ -205,7 +575,17 @@ impl eframe::App for ManagerUI {
                 ui.selectable_value(&mut self.selected_tab, Tab::Game, "In Game");
                 ui.selectable_value(&mut self.selected_tab, Tab::Backup, "In Backup");
                 ui.selectable_value(&mut self.selected_tab, Tab::Tap, "On TheAbsolute.Plus");
+                ui.selectable_value(&mut self.selected_tab, Tab::Bests, "Personal bests");
+                ui.selectable_value(&mut self.selected_tab, Tab::Progress, "Progress");
+                ui.selectable_value(&mut self.selected_tab, Tab::Sessions, "Sessions");
+                ui.selectable_value(&mut self.selected_tab, Tab::Daily, "Daily");
             });
+            if matches!(
+                self.selected_tab,
+                Tab::Bests | Tab::Progress | Tab::Sessions | Tab::Daily
+            ) {
+                return;
+            }
 
             ui.horizontal(|ui| {
                 ui.selectable_value(&mut self.selected_mode, Mode::Normal, "Normal");
@@ -220,26 +600,484 @@ impl eframe::App for ManagerUI {
                 ui.selectable_value(&mut self.selected_mode, Mode::Asuka, "Asuka");
                 ui.selectable_value(&mut self.selected_mode, Mode::Versus, "Versus");
             });
+
+            ui.horizontal(|ui| {
+                ui.label("Players:");
+                for filter in PlayerFilter::ALL {
+                    ui.selectable_value(&mut self.player_filter, filter, filter.label());
+                }
+                if self.friends.me.is_none() {
+                    ui.colored_label(ui.visuals().warn_fg_color, "⚠")
+                        .on_hover_text(
+                            "Set my_steam_id in the config or log into Steam to know who you are",
+                        );
+                }
+            });
         });
 
-        egui::CentralPanel::default().show(ctx, |ui| self.show_table(ui));
+        if let Some(replay) = &self.detail {
+            let mut open = true;
+            egui::SidePanel::right("replay_detail")
+                .default_width(280.0)
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.heading("Replay");
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            open = !ui.button("✖").clicked();
+                        });
+                    });
+                    show_replay_detail(
+                        ui,
+                        replay,
+                        &self.names,
+                        self.steam_id_format,
+                        &mut self.seed,
+                    );
+                });
+            if !open {
+                self.detail = None;
+            }
+        }
+        egui::CentralPanel::default().show(ctx, |ui| match self.selected_tab {
+            Tab::Bests => self.show_bests(ui),
+            Tab::Progress => self.show_progress(ui),
+            Tab::Sessions => self.show_sessions(ui),
+            Tab::Daily => self.show_daily(ui),
+            _ => self.show_table(ui),
+        });
         egui::TopBottomPanel::bottom("bottom_panel").show(ctx, |ui| {
             //TODO invert selection (set complementary magic)
-            ui.label("Test");
+            ui.horizontal(|ui| {
+                ui.label("Test");
+                let unsupported = &self.replay_store.unsupported;
+                if !unsupported.is_empty() {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        format!("{} replays from an unsupported build", unsupported.len()),
+                    )
+                    .on_hover_text(
+                        unsupported
+                            .iter()
+                            .map(|p| p.display().to_string())
+                            .collect::<Vec<_>>()
+                            .join("\n"),
+                    );
+                }
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    let diagnostics = [
+                        &self.replay_store.diagnostics,
+                        &self.names.steam_api.diagnostics,
+                        &self.names.aliases.diagnostics,
+                    ];
+                    let errors: usize = diagnostics.iter().map(|d| d.count(Level::Error)).sum();
+                    let total: usize = diagnostics.iter().map(|d| d.len()).sum();
+                    let badge = if errors > 0 {
+                        egui::RichText::new(format!("⚠ {errors} errors, {total} total"))
+                            .color(ui.visuals().error_fg_color)
+                    } else {
+                        egui::RichText::new(format!("Log ({total})"))
+                    };
+                    ui.toggle_value(&mut self.show_log, badge);
+                });
+            });
         });
+
+        if let Some(id) = self.player {
+            let mut open = true;
+            egui::Window::new(format!("Player: {}", self.names.get(id)))
+                .id(egui::Id::new("player_page"))
+                .open(&mut open)
+                .default_width(520.0)
+                .show(ctx, |ui| {
+                    show_player(
+                        ui,
+                        id,
+                        &self.replay_store,
+                        &self.names,
+                        self.steam_id_format,
+                    )
+                });
+            if !open {
+                self.player = None;
+            }
+        }
+
+        if let Some(seed) = self.seed {
+            let mut open = true;
+            egui::Window::new(format!("Seed {seed}"))
+                .id(egui::Id::new("same_seed"))
+                .open(&mut open)
+                .default_width(520.0)
+                .show(ctx, |ui| {
+                    show_same_seed(
+                        ui,
+                        seed,
+                        &self.replay_store,
+                        &self.names,
+                        &mut self.player,
+                        &mut self.detail,
+                    )
+                });
+            if !open {
+                self.seed = None;
+            }
+        }
+
+        egui::Window::new("Log")
+            .open(&mut self.show_log)
+            .default_width(600.0)
+            .show(ctx, |ui| {
+                show_log(
+                    ui,
+                    &[
+                        &self.replay_store.diagnostics,
+                        &self.names.steam_api.diagnostics,
+                        &self.names.aliases.diagnostics,
+                    ],
+                )
+            });
     }
 }
 
-fn toggle_row_selection(
-    selected_rows: &mut HashSet<usize>,
-    row_index: usize,
-    row_response: &egui::Response,
+fn show_log(ui: &mut egui::Ui, sources: &[&Diagnostics]) {
+    let mut diagnostics: Vec<&Diagnostic> = sources.iter().flat_map(|d| d.iter()).collect();
+    if diagnostics.is_empty() {
+        ui.label("Nothing to report.");
+        return;
+    }
+    diagnostics.sort_by_key(|d| d.timestamp);
+    egui::ScrollArea::both().show(ui, |ui| {
+        egui::Grid::new("log_grid").striped(true).show(ui, |ui| {
+            for d in diagnostics {
+                ui.label(d.timestamp.format("%H:%M:%S").to_string());
+                let color = match d.level {
+                    Level::Error => ui.visuals().error_fg_color,
+                    _ => ui.visuals().warn_fg_color,
+                };
+                ui.colored_label(color, d.kind.to_string());
+                ui.label(
+                    d.path
+                        .as_ref()
+                        .and_then(|p| p.file_name())
+                        .map(|f| f.to_string_lossy().into_owned())
+                        .unwrap_or_default(),
+                )
+                .on_hover_text(
+                    d.path
+                        .as_ref()
+                        .map(|p| p.display().to_string())
+                        .unwrap_or_default(),
+                );
+                ui.label(&d.message);
+                ui.end_row();
+            }
+        });
+    });
+}
+
+/// GitHub style, a column per week over the last year, darker when more Dailies were played.
+fn show_heatmap(ui: &mut egui::Ui, days: &BTreeMap<NaiveDate, usize>, today: NaiveDate) {
+    const WEEKS: i64 = 53;
+    const CELL: f32 = 10.0;
+    let first = today
+        - TimeDelta::days(today.weekday().num_days_from_monday() as i64)
+        - TimeDelta::weeks(WEEKS - 1);
+    let empty = ui.visuals().faint_bg_color;
+    let full = ui.visuals().selection.bg_fill;
+    ui.horizontal(|ui| {
+        ui.spacing_mut().item_spacing = egui::vec2(2.0, 2.0);
+        for week in 0..WEEKS {
+            ui.vertical(|ui| {
+                for weekday in 0..7 {
+                    let day = first + TimeDelta::days(week * 7 + weekday);
+                    if day > today {
+                        break;
+                    }
+                    let count = days.get(&day).copied().unwrap_or(0);
+                    let (rect, response) =
+                        ui.allocate_exact_size(egui::vec2(CELL, CELL), egui::Sense::hover());
+                    let color = if count == 0 {
+                        empty
+                    } else {
+                        empty.lerp_to_gamma(full, (count.min(4) as f32) / 4.0)
+                    };
+                    ui.painter().rect_filled(rect, 2.0, color);
+                    response.on_hover_text(format!("{day}: {count} Daily replays"));
+                }
+            });
+        }
+    });
+}
+
+fn avatar_image(path: &Path, size: f32) -> egui::Image<'static> {
+    egui::Image::new(format!("file://{}", path.display()))
+        .fit_to_exact_size(egui::vec2(size, size))
+        .rounding(2.0)
+}
+
+fn show_player(
+    ui: &mut egui::Ui,
+    id: SteamId,
+    store: &ReplayStore,
+    names: &NameChain,
+    format: SteamIdFormat,
 ) {
-    if row_response.clicked() {
-        if selected_rows.contains(&row_index) {
-            selected_rows.remove(&row_index);
-        } else {
-            selected_rows.insert(row_index);
+    let stats = PlayerStats::new(id, store.all());
+    let date = |d: Option<DateTime<Local>>| {
+        d.map(|d| d.format("%Y-%m-%d").to_string())
+            .unwrap_or_default()
+    };
+    ui.horizontal(|ui| {
+        if let Some(path) = names.steam_api.avatar(id, AvatarSize::Medium) {
+            ui.add(avatar_image(path, 64.0));
         }
+        ui.vertical(|ui| {
+            ui.heading(names.get(id));
+            ui.label(id.display(format).to_string());
+            ui.label(format!(
+                "{} replays, {} to {}",
+                stats.count,
+                date(stats.first_played),
+                date(stats.last_played)
+            ));
+        });
+    });
+    ui.separator();
+
+    ui.strong("Bests");
+    egui::Grid::new("player_bests")
+        .num_columns(6)
+        .striped(true)
+        .show(ui, |ui| {
+            for header in ["Mode", "Rule", "Replays", "Level", "Score", "Time"] {
+                ui.strong(header);
+            }
+            ui.end_row();
+            for ((mode, rule), m) in &stats.modes {
+                ui.label(mode);
+                ui.label(rule.to_string());
+                ui.label(m.count.to_string());
+                ui.label(m.best_level.to_string());
+                ui.label(m.best_score.to_string());
+                ui.label(m.best.time.to_string())
+                    .on_hover_text("Time of the best run");
+                ui.end_row();
+            }
+        });
+
+    if !stats.versus.is_empty() {
+        ui.separator();
+        ui.strong("Versus");
+        //TODO show wins once the result is decoded
+        ui.label("Who got further, the winner isn't decoded yet.");
+        egui::Grid::new("player_head_to_head")
+            .num_columns(5)
+            .striped(true)
+            .show(ui, |ui| {
+                for header in ["Opponent", "Matches", "Got further", "Fell short", "Levels"] {
+                    ui.strong(header);
+                }
+                ui.end_row();
+                let row = |ui: &mut egui::Ui, name: &str, record: HeadToHead| {
+                    ui.label(name);
+                    ui.label(record.matches.to_string());
+                    ui.label(record.ahead.to_string());
+                    ui.label(record.behind.to_string());
+                    ui.label(format!("{:+.0} on average", record.mean_level_difference()))
+                        .on_hover_text(format!("{:+} in total", record.level_difference));
+                    ui.end_row();
+                };
+                for (opponent, record) in head_to_head(&stats.versus) {
+                    row(ui, names.get(opponent), record);
+                }
+                row(ui, "All opponents", HeadToHead::of(&stats.versus));
+            });
+        ui.add_space(4.0);
+        egui::Grid::new("player_versus")
+            .num_columns(4)
+            .striped(true)
+            .show(ui, |ui| {
+                for header in ["Date", "Opponent", "Level", "Their level"] {
+                    ui.strong(header);
+                }
+                ui.end_row();
+                for m in &stats.versus {
+                    ui.label(m.player.played_at.format("%Y-%m-%d %H:%M").to_string());
+                    ui.label(names.get(m.opponent.steamid));
+                    ui.label(m.player.level.to_string());
+                    ui.label(m.opponent.level.to_string());
+                    ui.end_row();
+                }
+            });
+    }
+
+    ui.separator();
+    show_name_history(ui, names.steam_api.cached(id), None);
+}
+
+fn show_replay_detail(
+    ui: &mut egui::Ui,
+    replay: &Replay,
+    names: &NameChain,
+    format: SteamIdFormat,
+    seed: &mut Option<u32>,
+) {
+    if let Some(path) = names.steam_api.avatar(replay.steamid, AvatarSize::Medium) {
+        ui.add(avatar_image(path, 64.0));
+    }
+    egui::Grid::new("replay_detail_grid")
+        .num_columns(2)
+        .striped(true)
+        .show(ui, |ui| {
+            let mut field = |name: &str, value: String| {
+                ui.label(name);
+                ui.label(value);
+                ui.end_row();
+            };
+            field("Player", names.get(replay.steamid).to_string());
+            let mut steam_id = replay.steamid.display(format).to_string();
+            if !replay.steamid.is_player() {
+                steam_id.push_str(" (spoofed)");
+            }
+            field("Steam ID", steam_id);
+            field("Mode", format!("{:?}", replay.mode));
+            field("Rule", replay.rule.to_string());
+            field(
+                "Played",
+                replay.played_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+            );
+            field("Level", replay.level.to_string());
+            field("Playtime", replay.time.to_string());
+            field("Score", replay.score.to_string());
+            field("Seed", replay.seed.to_string());
+            let modifiers = replay.modifiers.iter().map(|m| m.to_string());
+            field("Options", modifiers.collect::<Vec<_>>().join(", "));
+            if let Some(opponent) = replay.opponent {
+                field(
+                    "Opponent",
+                    format!("seed {}, {}", opponent.seed, opponent.rule),
+                );
+            }
+            field("Integrity", replay.integrity.to_string());
+        });
+    if ui.button("Find replays with this seed").clicked() {
+        *seed = Some(replay.seed);
+    }
+    ui.separator();
+    show_name_history(
+        ui,
+        names.steam_api.cached(replay.steamid),
+        Some(replay.played_at.to_utc()),
+    );
+}
+
+/// Everyone's replays of a seed, to compare how they handled the same pieces.
+fn show_same_seed(
+    ui: &mut egui::Ui,
+    seed: u32,
+    store: &ReplayStore,
+    names: &NameChain,
+    player: &mut Option<SteamId>,
+    detail: &mut Option<Replay>,
+) {
+    let groups = same_seed(seed, store.all());
+    if groups.is_empty() {
+        ui.label("No replays with this seed.");
+        return;
+    }
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        for ((mode, rule), replays) in &groups {
+            ui.strong(format!("{mode} ({rule})"));
+            egui::Grid::new(("same_seed", mode, *rule))
+                .num_columns(6)
+                .striped(true)
+                .show(ui, |ui| {
+                    for header in ["#", "Name", "Level", "Score", "Time", "Date"] {
+                        ui.strong(header);
+                    }
+                    ui.end_row();
+                    for (i, replay) in replays.iter().enumerate() {
+                        ui.label((i + 1).to_string());
+                        if ui.link(names.get(replay.steamid)).clicked() {
+                            *player = Some(replay.steamid);
+                        }
+                        ui.label(replay.level.to_string());
+                        ui.label(replay.score.to_string());
+                        ui.label(replay.time.to_string());
+                        let date = replay.played_at.format("%Y-%m-%d %H:%M");
+                        if ui.link(date.to_string()).clicked() {
+                            *detail = Some((*replay).clone());
+                        }
+                        ui.end_row();
+                    }
+                });
+            ui.add_space(8.0);
+        }
+    });
+}
+
+/// Every persona seen for a player, marking the one they likely had at `played_at`.
+fn show_name_history(
+    ui: &mut egui::Ui,
+    cached: Option<&CachedName>,
+    played_at: Option<DateTime<Utc>>,
+) {
+    ui.strong("Names seen");
+    let Some(cached) = cached.filter(|c| !c.history.is_empty()) else {
+        ui.label("No persona fetched from the Web API yet.");
+        return;
+    };
+    let at_the_time = played_at.and_then(|when| cached.persona_at(when));
+    egui::Grid::new("name_history_grid")
+        .num_columns(3)
+        .striped(true)
+        .show(ui, |ui| {
+            for persona in &cached.history {
+                if at_the_time.is_some_and(|p| std::ptr::eq(p, persona)) {
+                    ui.strong(&persona.name)
+                        .on_hover_text("Most likely name when this was played");
+                } else {
+                    ui.label(&persona.name);
+                }
+                ui.label(persona.first_seen.format("%Y-%m-%d").to_string());
+                ui.label(persona.last_seen.format("%Y-%m-%d").to_string());
+                ui.end_row();
+            }
+        });
+}
+
+fn alias_menu(
+    ui: &mut egui::Ui,
+    names: &mut NameChain,
+    alias_edit: &mut Option<(SteamId, String)>,
+    id: SteamId,
+    format: SteamIdFormat,
+) {
+    if alias_edit.as_ref().is_none_or(|(edited, _)| *edited != id) {
+        *alias_edit = Some((id, names.get(id).to_string()));
+    }
+    let Some((_, text)) = alias_edit else {
+        return;
+    };
+    ui.label(format!("Alias for {}", id.display(format)));
+    let edit = ui.text_edit_singleline(text);
+    if edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter))
+        || ui.button("Save").clicked()
+    {
+        names.aliases.set(id, text);
+        *alias_edit = None;
+        ui.close_menu();
+    } else if names.aliases.resolve(id).is_some() && ui.button("Remove alias").clicked() {
+        names.aliases.set(id, "");
+        *alias_edit = None;
+        ui.close_menu();
+    }
+}
+
+fn toggle_row_selection(selected_rows: &mut HashSet<usize>, row_index: usize) {
+    if selected_rows.contains(&row_index) {
+        selected_rows.remove(&row_index);
+    } else {
+        selected_rows.insert(row_index);
     }
 }
@@ -0,0 +1,166 @@
+//! Just enough of Valve's text KeyValues format to read the Steam client's config files.
+
+use std::{iter::Peekable, str::Chars};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Vdf {
+    Str(String),
+    Obj(Vec<(String, Vdf)>),
+}
+
+impl Vdf {
+    /// Keys are case-insensitive, Steam isn't consistent about them.
+    pub fn get(&self, key: &str) -> Option<&Vdf> {
+        match self {
+            Vdf::Obj(entries) => entries
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(key))
+                .map(|(_, v)| v),
+            Vdf::Str(_) => None,
+        }
+    }
+
+    pub fn get_path(&self, path: &[&str]) -> Option<&Vdf> {
+        path.iter().try_fold(self, |node, key| node.get(key))
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Vdf::Str(s) => Some(s),
+            Vdf::Obj(_) => None,
+        }
+    }
+
+    pub fn entries(&self) -> &[(String, Vdf)] {
+        match self {
+            Vdf::Obj(entries) => entries,
+            Vdf::Str(_) => &[],
+        }
+    }
+}
+
+/// Parses a whole file as an object, `None` when it isn't valid KeyValues.
+pub fn parse(text: &str) -> Option<Vdf> {
+    let mut chars = text.chars().peekable();
+    let entries = parse_entries(&mut chars, false)?;
+    Some(Vdf::Obj(entries))
+}
+
+enum Token {
+    Str(String),
+    Open,
+    Close,
+}
+
+fn parse_entries(chars: &mut Peekable<Chars<'_>>, nested: bool) -> Option<Vec<(String, Vdf)>> {
+    let mut entries = Vec::new();
+    loop {
+        let key = match next_token(chars) {
+            Some(Token::Str(key)) => key,
+            Some(Token::Close) if nested => return Some(entries),
+            None if !nested => return Some(entries),
+            _ => return None,
+        };
+        let value = match next_token(chars)? {
+            Token::Str(value) => Vdf::Str(value),
+            Token::Open => Vdf::Obj(parse_entries(chars, true)?),
+            Token::Close => return None,
+        };
+        entries.push((key, value));
+    }
+}
+
+fn next_token(chars: &mut Peekable<Chars<'_>>) -> Option<Token> {
+    loop {
+        match chars.peek()? {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '/' => {
+                // `//` comments run to the end of the line
+                chars.find(|c| *c == '\n');
+            }
+            '[' => {
+                // platform conditionals like `[$WIN32]`, we don't care about them
+                chars.find(|c| *c == ']');
+            }
+            '{' => {
+                chars.next();
+                return Some(Token::Open);
+            }
+            '}' => {
+                chars.next();
+                return Some(Token::Close);
+            }
+            '"' => {
+                chars.next();
+                let mut s = String::new();
+                loop {
+                    match chars.next()? {
+                        '"' => return Some(Token::Str(s)),
+                        '\\' => match chars.next()? {
+                            'n' => s.push('\n'),
+                            't' => s.push('\t'),
+                            c => s.push(c),
+                        },
+                        c => s.push(c),
+                    }
+                }
+            }
+            _ => {
+                let mut s = String::new();
+                while let Some(c) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, '{' | '}' | '"') {
+                        break;
+                    }
+                    s.push(*c);
+                    chars.next();
+                }
+                return Some(Token::Str(s));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let vdf = parse(
+            r#"
+            // written by the client
+            "users"
+            {
+                "76561197960435530"
+                {
+                    "AccountName"   "robin"
+                    "PersonaName"   "Robin \"RW\" Walker"
+                    "MostRecent"    "1"
+                }
+                unquoted value [$WIN32]
+            }
+            "#,
+        )
+        .unwrap();
+        let user = vdf.get_path(&["users", "76561197960435530"]).unwrap();
+        assert_eq!(
+            user.get("personaname").and_then(Vdf::as_str),
+            Some("Robin \"RW\" Walker")
+        );
+        assert_eq!(user.entries().len(), 3);
+        assert_eq!(
+            vdf.get_path(&["users", "unquoted"]).and_then(Vdf::as_str),
+            Some("value")
+        );
+        assert!(vdf.get_path(&["users", "nobody"]).is_none());
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(parse(r#""users" { "a" "b" "#).is_none());
+        assert!(parse(r#""users" } "#).is_none());
+        assert_eq!(parse(""), Some(Vdf::Obj(Vec::new())));
+    }
+}
@@ -0,0 +1,116 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use serde_json::Value;
+
+/// A tiny HTTP server on localhost standing in for the Steam Web API.
+pub struct StubServer {
+    pub url: String,
+    /// Request targets (path and query) in the order they were received.
+    pub requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl StubServer {
+    /// Answers `GetPlayerSummaries` with the players of `fixture` whose ids were asked for,
+    /// leaving out unknown ones like the real API does. Avatar URLs point back at the stub,
+    /// which serves [`avatar_bytes`] for them.
+    pub fn player_summaries(fixture: &str) -> Self {
+        let fixture: Value = serde_json::from_str(fixture).unwrap();
+        Self::spawn(None, move |url, target| {
+            if let Some(file) = target.strip_prefix("/avatars/") {
+                return (200, avatar_bytes(file));
+            }
+            let ids = query_param(target, "steamids").unwrap_or_default();
+            let ids: Vec<&str> = ids.split(',').collect();
+            let players: Vec<&Value> = fixture["response"]["players"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter(|p| ids.contains(&p["steamid"].as_str().unwrap()))
+                .collect();
+            let body = serde_json::json!({ "response": { "players": players } })
+                .to_string()
+                .replace("https://avatars.steamstatic.com", &format!("{url}/avatars"));
+            (200, body.into_bytes())
+        })
+    }
+
+    /// Answers with the canned `(status, body)` pairs in order, then stops.
+    pub fn canned(responses: Vec<(u16, &'static str)>) -> Self {
+        let count = responses.len();
+        let responses = Mutex::new(responses.into_iter());
+        Self::spawn(Some(count), move |_, _| {
+            let (status, body) = responses.lock().unwrap().next().unwrap();
+            (status, body.as_bytes().to_vec())
+        })
+    }
+
+    fn spawn(
+        limit: Option<usize>,
+        handler: impl Fn(&str, &str) -> (u16, Vec<u8>) + Send + 'static,
+    ) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+        let base = url.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().take(limit.unwrap_or(usize::MAX)) {
+                let mut stream = stream.unwrap();
+                let target = read_request(&stream);
+                seen.lock().unwrap().push(target.clone());
+                let (status, body) = handler(&base, &target);
+                respond(&mut stream, status, &body);
+            }
+        });
+        Self { url, requests }
+    }
+
+    pub fn request_count(&self) -> usize {
+        self.requests.lock().unwrap().len()
+    }
+}
+
+/// Stands in for an image, nothing decodes it in the tests.
+pub fn avatar_bytes(file: &str) -> Vec<u8> {
+    format!("avatar {file}").into_bytes()
+}
+
+pub fn query_param(target: &str, name: &str) -> Option<String> {
+    let (_, query) = target.split_once('?')?;
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.replace("%2C", ","))
+}
+
+fn read_request(stream: &TcpStream) -> String {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    let target = line
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or_default()
+        .to_string();
+    while line != "\r\n" && !line.is_empty() {
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+    }
+    target
+}
+
+fn respond(stream: &mut TcpStream, status: u16, body: &[u8]) {
+    write!(
+        stream,
+        "HTTP/1.1 {status} Stub\r\nContent-Length: {}\r\nRetry-After: 0\r\nConnection: close\r\n\r\n",
+        body.len()
+    )
+    .unwrap();
+    stream.write_all(body).unwrap();
+}
@@ -0,0 +1,314 @@
+mod common;
+
+use std::{
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+use chrono::TimeDelta;
+use common::{StubServer, avatar_bytes, query_param};
+use log::Level;
+use tgm4_replay_manager::{
+    name_cache::NameCache,
+    steam::{AvatarSize, Polled, RetryPolicy, SteamApi, SteamError, TokenBucket},
+    steam_id::SteamId,
+};
+
+const FIXTURE: &str = include_str!("fixtures/player_summaries.json");
+
+const ROBIN: SteamId = SteamId::from_account_id(169802);
+const TETRA: SteamId = SteamId::from_account_id(41595176);
+const GM: SteamId = SteamId::from_account_id(52079950);
+
+fn api(server: &StubServer) -> SteamApi {
+    SteamApi::new(String::from("SECRETKEY"))
+        .with_base_url(&format!("{}/", server.url))
+        .with_throttle(TokenBucket::new(100, 1000.0))
+        .with_retry(RetryPolicy {
+            max_retries: 2,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(10),
+        })
+}
+
+#[test]
+fn resolves_names_from_player_summaries() {
+    let server = StubServer::player_summaries(FIXTURE);
+    let mut api = api(&server);
+    #[allow(clippy::zero_prefixed_literal)]
+    let spoofed = SteamId::from(0101);
+    let summary = api.add_ids(&[ROBIN, spoofed, TETRA]).unwrap();
+
+    assert_eq!(summary.succeeded().count(), 1);
+    assert_eq!(summary.spoofed, 1);
+    assert_eq!(api.get(ROBIN), "Robin");
+    assert_eq!(api.get(TETRA), "Tetra Master");
+    assert_eq!(api.get(spoofed), "Unknown/Spoofed");
+    assert_eq!(api.get(GM), "Unknown/Unparsed");
+
+    let requests = server.requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert!(requests[0].starts_with("/ISteamUser/GetPlayerSummaries/v0002/?"));
+    assert_eq!(query_param(&requests[0], "key").unwrap(), "SECRETKEY");
+    // spoofed ids never reach the API
+    assert_eq!(
+        query_param(&requests[0], "steamids").unwrap(),
+        "76561197960435530,76561198001860904"
+    );
+}
+
+#[test]
+fn requests_at_most_100_ids_at_once() {
+    let server = StubServer::player_summaries(FIXTURE);
+    let mut api = api(&server);
+    let mut ids: Vec<SteamId> = (1..=150).map(SteamId::from_account_id).collect();
+    ids.push(GM);
+    let summary = api.add_ids(&ids).unwrap();
+
+    assert_eq!(summary.chunks.len(), 2);
+    assert_eq!(summary.chunks[0].ids.len(), 100);
+    assert_eq!(summary.chunks[1].ids.len(), 51);
+    assert_eq!(server.request_count(), 2);
+    assert_eq!(api.get(GM), "ＧＭ　ｎｏｗ");
+}
+
+#[test]
+fn only_fetches_stale_ids() {
+    let server = StubServer::player_summaries(FIXTURE);
+    let path = std::env::temp_dir()
+        .join(format!("tgm4-steam-api-{}", std::process::id()))
+        .join("names.json");
+    let mut api = api(&server).with_cache(NameCache::load(path.clone()), TimeDelta::days(1));
+    api.add_ids(&[ROBIN]).unwrap();
+    assert_eq!(server.request_count(), 1);
+
+    // a fresh client picks the name up from disk and doesn't ask again
+    let mut api = self::api(&server).with_cache(NameCache::load(path.clone()), TimeDelta::days(1));
+    let summary = api.add_ids(&[ROBIN, TETRA]).unwrap();
+    assert_eq!(summary.cached, 1);
+    let requests = server.requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(
+        query_param(&requests[1], "steamids").unwrap(),
+        "76561198001860904"
+    );
+    assert_eq!(api.get(ROBIN), "Robin");
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn retries_transient_errors() {
+    let server = StubServer::canned(vec![(503, ""), (429, ""), (200, FIXTURE)]);
+    let mut api = api(&server);
+    let summary = api.add_ids(&[ROBIN]).unwrap();
+    assert_eq!(summary.failed().count(), 0);
+    assert_eq!(server.request_count(), 3);
+    assert_eq!(api.get(ROBIN), "Robin");
+}
+
+#[test]
+fn gives_up_after_retries() {
+    let server = StubServer::canned(vec![(500, ""), (502, ""), (500, "")]);
+    let mut api = api(&server);
+    let summary = api.add_ids(&[ROBIN]).unwrap();
+    assert_eq!(server.request_count(), 3);
+    let failed: Vec<_> = summary.failed().collect();
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].ids, vec![ROBIN]);
+    assert_eq!(failed[0].error, Some(SteamError::Status(500)));
+    assert_eq!(api.get(ROBIN), "Unknown/Unparsed");
+    assert_eq!(api.diagnostics.len(), 1);
+}
+
+#[test]
+fn reports_typed_errors() {
+    let server = StubServer::canned(vec![(403, "")]);
+    assert_eq!(
+        api(&server).add_ids(&[ROBIN, TETRA]).unwrap_err(),
+        SteamError::BadKey
+    );
+    assert_eq!(server.request_count(), 1);
+    // a 401 outside GetFriendList is still the key
+    let server = StubServer::canned(vec![(401, "")]);
+    assert_eq!(
+        api(&server).add_ids(&[ROBIN, TETRA]).unwrap_err(),
+        SteamError::BadKey
+    );
+
+    let server = StubServer::canned(vec![(200, "nope")]);
+    let summary = api(&server).add_ids(&[ROBIN]).unwrap();
+    assert!(matches!(
+        summary.failed().next().unwrap().error,
+        Some(SteamError::Json(_))
+    ));
+}
+
+#[test]
+fn keeps_the_key_out_of_diagnostics() {
+    let mut api = SteamApi::new(String::from("SECRETKEY"))
+        .with_base_url("http://SECRETKEY.invalid")
+        .with_retry(RetryPolicy {
+            max_retries: 0,
+            ..Default::default()
+        });
+    let summary = api.add_ids(&[ROBIN]).unwrap();
+    assert!(matches!(
+        summary.failed().next().unwrap().error,
+        Some(SteamError::Network(_))
+    ));
+    assert!(
+        api.diagnostics
+            .iter()
+            .all(|d| !d.message.contains("SECRETKEY"))
+    );
+}
+
+#[test]
+fn caches_avatars_on_disk() {
+    let server = StubServer::player_summaries(FIXTURE);
+    let dir = std::env::temp_dir().join(format!("tgm4-avatars-{}", std::process::id()));
+    let ids = [ROBIN, TETRA, GM, SteamId::from_account_id(1)];
+    let mut api = api(&server)
+        .with_cache(NameCache::load(dir.join("names.json")), TimeDelta::days(1))
+        .with_avatar_dir(dir.join("avatars"));
+    api.add_ids(&ids).unwrap();
+
+    // the last two players share an avatar, the unknown id has none
+    assert_eq!(api.fetch_avatars(&ids), 4);
+    assert!(api.diagnostics.is_empty());
+    let small = api.avatar(TETRA, AvatarSize::Small).unwrap();
+    assert_eq!(small, api.avatar(GM, AvatarSize::Small).unwrap());
+    assert_eq!(
+        std::fs::read(small).unwrap(),
+        avatar_bytes("fef49e7fa7e1997310d705b2a6158ff8dc1cdfeb.jpg")
+    );
+    assert!(api.avatar(ROBIN, AvatarSize::Medium).is_some());
+    assert!(api.avatar(ids[3], AvatarSize::Small).is_none());
+
+    // a fresh client finds the URLs in the name cache and the images on disk
+    let requests = server.request_count();
+    let mut api = self::api(&server)
+        .with_cache(NameCache::load(dir.join("names.json")), TimeDelta::days(1))
+        .with_avatar_dir(dir.join("avatars"));
+    assert_eq!(api.fetch_avatars(&ids), 0);
+    assert_eq!(server.request_count(), requests);
+    assert!(api.avatar(ROBIN, AvatarSize::Small).is_some());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn downloads_avatars_in_the_background() {
+    let server = StubServer::player_summaries(FIXTURE);
+    let dir = std::env::temp_dir().join(format!("tgm4-avatars-bg-{}", std::process::id()));
+    let ids = [ROBIN, TETRA, GM];
+    let mut api = api(&server)
+        .with_cache(NameCache::load(dir.join("names.json")), TimeDelta::days(1))
+        .with_avatar_dir(dir.join("avatars"));
+    api.add_ids(&ids).unwrap();
+
+    // the downloads share the throttle with the API calls, one every 50ms
+    let mut api = api.with_throttle(TokenBucket::new(1, 20.0));
+    let start = Instant::now();
+    let (sender, arrivals) = mpsc::channel();
+    api.fetch_avatars_in_background(&ids, move || sender.send(()).unwrap());
+    assert!(api.avatar(ROBIN, AvatarSize::Small).is_none());
+    for _ in 0..6 {
+        arrivals.recv_timeout(Duration::from_secs(5)).unwrap();
+    }
+    assert!(start.elapsed() >= Duration::from_millis(150));
+    assert_eq!(api.poll().avatars, 4);
+    assert!(api.diagnostics.is_empty());
+    assert!(api.avatar(ROBIN, AvatarSize::Medium).is_some());
+    assert_eq!(
+        api.avatar(TETRA, AvatarSize::Small),
+        api.avatar(GM, AvatarSize::Small)
+    );
+    assert_eq!(api.poll(), Polled::default());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn fetches_names_in_the_background() {
+    let server = StubServer::player_summaries(FIXTURE);
+    let dir = std::env::temp_dir().join(format!("tgm4-names-bg-{}", std::process::id()));
+    let ids = [ROBIN, TETRA, GM];
+    let mut api = api(&server)
+        .with_cache(NameCache::load(dir.join("names.json")), TimeDelta::days(1))
+        .with_avatar_dir(dir.join("avatars"));
+    let (sender, arrivals) = mpsc::channel();
+    api.fetch_in_background(&ids, move || sender.send(()).unwrap());
+    // nothing changes until polled
+    assert_eq!(api.get(ROBIN), "Unknown/Unparsed");
+
+    // one chunk of names, then the avatars of the players it returned
+    for _ in 0..7 {
+        arrivals.recv_timeout(Duration::from_secs(5)).unwrap();
+    }
+    assert_eq!(
+        api.poll(),
+        Polled {
+            names: 3,
+            avatars: 4,
+            friends: None
+        }
+    );
+    assert!(api.diagnostics.is_empty());
+    assert_eq!(api.get(TETRA), "Tetra Master");
+    assert!(api.avatar(GM, AvatarSize::Medium).is_some());
+    assert!(dir.join("names.json").exists());
+
+    // a refused key stops the names, cached avatars still come in
+    let server = StubServer::canned(vec![(403, "")]);
+    let mut api = self::api(&server)
+        .with_cache(NameCache::load(dir.join("names.json")), TimeDelta::zero())
+        .with_avatar_dir(dir.join("avatars"));
+    let (sender, arrivals) = mpsc::channel();
+    api.fetch_in_background(&ids, move || sender.send(()).unwrap());
+    for _ in 0..7 {
+        arrivals.recv_timeout(Duration::from_secs(5)).unwrap();
+    }
+    assert_eq!(api.poll(), Polled::default());
+    assert_eq!(server.request_count(), 1);
+    assert_eq!(api.diagnostics.len(), 1);
+    assert_eq!(api.get(ROBIN), "Robin");
+    assert!(api.avatar(ROBIN, AvatarSize::Small).is_some());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn fetches_friend_lists() {
+    let server = StubServer::canned(vec![
+        (200, include_str!("fixtures/friend_list.json")),
+        (401, ""),
+    ]);
+    let mut api = api(&server);
+    assert_eq!(api.friend_list(TETRA).unwrap(), [ROBIN, GM]);
+    let requests = server.requests.lock().unwrap().clone();
+    assert!(requests[0].starts_with("/ISteamUser/GetFriendList/v0001/?"));
+    assert_eq!(
+        query_param(&requests[0], "steamid").unwrap(),
+        "76561198001860904"
+    );
+
+    // private friend lists come back as 401
+    assert_eq!(api.friend_list(TETRA).unwrap_err(), SteamError::Private);
+    assert_eq!(api.diagnostics.count(Level::Warn), 1);
+}
+
+#[test]
+fn fetches_friend_lists_in_the_background() {
+    let server = StubServer::canned(vec![
+        (200, include_str!("fixtures/friend_list.json")),
+        (403, ""),
+    ]);
+    let mut api = api(&server);
+    for expected in [Some(vec![ROBIN, GM]), None] {
+        let (sender, arrivals) = mpsc::channel();
+        api.fetch_friends_in_background(TETRA, move || sender.send(()).unwrap());
+        arrivals.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(api.poll().friends, expected);
+    }
+    // a refused key is an error, not a private list
+    assert_eq!(api.diagnostics.count(Level::Error), 1);
+    assert_eq!(api.diagnostics.len(), 1);
+}